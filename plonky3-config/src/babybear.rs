@@ -0,0 +1,105 @@
+//! BabyBear with FRI over its two-adic subgroups and a degree-4 or degree-5 challenge extension.
+
+use p3_baby_bear::{BabyBear, DiffusionMatrixBabyBear};
+use p3_challenger::{DuplexChallenger, HashChallenger, SerializingChallenger32};
+use p3_commit::ExtensionMmcs;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{Field, HasTwoAdicBionmialExtension, TwoAdicField};
+use p3_fri::TwoAdicFriPcs;
+use p3_merkle_tree::FieldMerkleTreeMmcs;
+use p3_poseidon2::{Poseidon2, Poseidon2ExternalMatrixGeneral};
+use p3_symmetric::{
+    CompressionFunctionFromHasher, CryptographicHasher, PaddingFreeSponge, SerializingHasher32,
+    TruncatedPermutation,
+};
+use p3_uni_stark::StarkConfig;
+use prover_serve::field::FieldId;
+use prover_serve::fri::FriParams;
+
+use crate::dft::Dft;
+use crate::perm::{self, Poseidon2Field};
+use crate::stark::Stark;
+
+const WIDTH: usize = 16;
+
+pub type Val = BabyBear;
+pub type Challenge<const D: usize> = BinomialExtensionField<Val, D>;
+
+pub type Perm = Poseidon2<Val, Poseidon2ExternalMatrixGeneral, DiffusionMatrixBabyBear, 16, 7>;
+pub type Poseidon2Hash = PaddingFreeSponge<Perm, 16, 8, 8>;
+pub type Poseidon2Compress = TruncatedPermutation<Perm, 2, 8, 16>;
+pub type Poseidon2Mmcs = FieldMerkleTreeMmcs<
+    <Val as Field>::Packing,
+    <Val as Field>::Packing,
+    Poseidon2Hash,
+    Poseidon2Compress,
+    8,
+>;
+pub type Poseidon2Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+pub type Poseidon2Config<const D: usize> = StarkConfig<
+    TwoAdicFriPcs<Val, Dft, Poseidon2Mmcs, ExtensionMmcs<Val, Challenge<D>, Poseidon2Mmcs>>,
+    Challenge<D>,
+    Poseidon2Challenger,
+>;
+
+/// Merkle trees and challenger over a 32-byte hash `H` of the elements' canonical `u32`s.
+pub type ByteMmcs<H> = FieldMerkleTreeMmcs<
+    Val,
+    u8,
+    SerializingHasher32<H>,
+    CompressionFunctionFromHasher<u8, H, 2, 32>,
+    32,
+>;
+pub type ByteChallenger<H> = SerializingChallenger32<Val, HashChallenger<u8, H, 32>>;
+pub type ByteConfig<H, const D: usize> = StarkConfig<
+    TwoAdicFriPcs<Val, Dft, ByteMmcs<H>, ExtensionMmcs<Val, Challenge<D>, ByteMmcs<H>>>,
+    Challenge<D>,
+    ByteChallenger<H>,
+>;
+
+/// Round constants of the Poseidon2 permutation used for hashing, compression and the challenger.
+pub type Poseidon2Constants = perm::Poseidon2Constants<Val, WIDTH>;
+
+impl Poseidon2Field for Val {
+    const FIELD: FieldId = FieldId::BabyBear;
+    const SBOX_DEGREE: u64 = 7;
+}
+
+pub fn poseidon2_stark<const D: usize>(
+    perm: &Perm,
+    dft: Dft,
+    fri: &FriParams,
+) -> Stark<Poseidon2Config<D>>
+where
+    Val: HasTwoAdicBionmialExtension<D>,
+{
+    let val_mmcs = Poseidon2Mmcs::new(
+        Poseidon2Hash::new(perm.clone()),
+        Poseidon2Compress::new(perm.clone()),
+    );
+    Stark::from_mmcs(
+        val_mmcs,
+        Poseidon2Challenger::new(perm.clone()),
+        fri,
+        Val::TWO_ADICITY,
+        |mmcs, fri| TwoAdicFriPcs::new(dft, mmcs, fri),
+    )
+}
+
+pub fn byte_stark<H, const D: usize>(hash: H, dft: Dft, fri: &FriParams) -> Stark<ByteConfig<H, D>>
+where
+    H: CryptographicHasher<u8, [u8; 32]> + Copy,
+    Val: HasTwoAdicBionmialExtension<D>,
+{
+    let val_mmcs = ByteMmcs::new(
+        SerializingHasher32::new(hash),
+        CompressionFunctionFromHasher::new(hash),
+    );
+    Stark::from_mmcs(
+        val_mmcs,
+        ByteChallenger::from_hasher(vec![], hash),
+        fri,
+        Val::TWO_ADICITY,
+        |mmcs, fri| TwoAdicFriPcs::new(dft, mmcs, fri),
+    )
+}
@@ -0,0 +1,74 @@
+//! A DFT picked at startup rather than at compile time, so that the configurations of two-adic
+//! fields are not duplicated for each DFT.
+
+use p3_dft::{Radix2Bowers, Radix2Dit, Radix2DitParallel, TwoAdicSubgroupDft};
+use p3_field::TwoAdicField;
+use p3_matrix::bitrev::{BitReversableMatrix, BitReversedMatrixView};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::util::reverse_matrix_index_bits;
+use prover_serve::dft::DftId;
+
+#[derive(Clone)]
+pub enum Dft {
+    Radix2DitParallel(Radix2DitParallel),
+    Radix2Bowers(Radix2Bowers),
+    Radix2Dit(Radix2Dit),
+}
+
+impl From<DftId> for Dft {
+    fn from(id: DftId) -> Self {
+        match id {
+            DftId::Radix2DitParallel => Dft::Radix2DitParallel(Radix2DitParallel::default()),
+            DftId::Radix2Bowers => Dft::Radix2Bowers(Radix2Bowers::default()),
+            DftId::Radix2Dit => Dft::Radix2Dit(Radix2Dit::default()),
+        }
+    }
+}
+
+impl Default for Dft {
+    fn default() -> Self {
+        Dft::from(DftId::default())
+    }
+}
+
+/// Evaluations come out the way [`Radix2DitParallel`] leaves them, stored in bit-reversed order
+/// behind a view; the other DFTs' are permuted in place to match.
+impl<F: TwoAdicField> TwoAdicSubgroupDft<F> for Dft {
+    type Evaluations = BitReversedMatrixView<RowMajorMatrix<F>>;
+
+    fn dft_batch(&self, mat: RowMajorMatrix<F>) -> Self::Evaluations {
+        match self {
+            Dft::Radix2DitParallel(dft) => dft.dft_batch(mat),
+            Dft::Radix2Bowers(dft) => bit_reversed(dft.dft_batch(mat)),
+            Dft::Radix2Dit(dft) => bit_reversed(dft.dft_batch(mat)),
+        }
+    }
+
+    fn idft_batch(&self, mat: RowMajorMatrix<F>) -> RowMajorMatrix<F> {
+        match self {
+            Dft::Radix2DitParallel(dft) => dft.idft_batch(mat),
+            Dft::Radix2Bowers(dft) => dft.idft_batch(mat),
+            Dft::Radix2Dit(dft) => dft.idft_batch(mat),
+        }
+    }
+
+    fn coset_lde_batch(
+        &self,
+        mat: RowMajorMatrix<F>,
+        added_bits: usize,
+        shift: F,
+    ) -> Self::Evaluations {
+        match self {
+            Dft::Radix2DitParallel(dft) => dft.coset_lde_batch(mat, added_bits, shift),
+            Dft::Radix2Bowers(dft) => bit_reversed(dft.coset_lde_batch(mat, added_bits, shift)),
+            Dft::Radix2Dit(dft) => bit_reversed(dft.coset_lde_batch(mat, added_bits, shift)),
+        }
+    }
+}
+
+fn bit_reversed<F: Clone + Send + Sync>(
+    mut evals: RowMajorMatrix<F>,
+) -> BitReversedMatrixView<RowMajorMatrix<F>> {
+    reverse_matrix_index_bits(&mut evals);
+    evals.bit_reverse_rows()
+}
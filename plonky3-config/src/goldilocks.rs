@@ -0,0 +1,98 @@
+//! Goldilocks with FRI over its two-adic subgroups and a degree-2 challenge extension. Its
+//! Poseidon2 is 8 elements wide.
+
+use p3_challenger::{DuplexChallenger, HashChallenger, SerializingChallenger64};
+use p3_commit::ExtensionMmcs;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{Field, TwoAdicField};
+use p3_fri::TwoAdicFriPcs;
+use p3_goldilocks::{DiffusionMatrixGoldilocks, Goldilocks};
+use p3_merkle_tree::FieldMerkleTreeMmcs;
+use p3_poseidon2::{Poseidon2, Poseidon2ExternalMatrixGeneral};
+use p3_symmetric::{
+    CompressionFunctionFromHasher, CryptographicHasher, PaddingFreeSponge, SerializingHasher64,
+    TruncatedPermutation,
+};
+use p3_uni_stark::StarkConfig;
+use prover_serve::field::FieldId;
+use prover_serve::fri::FriParams;
+
+use crate::dft::Dft;
+use crate::perm::{self, Poseidon2Field};
+use crate::stark::Stark;
+
+const WIDTH: usize = 8;
+
+pub type Val = Goldilocks;
+pub type Challenge = BinomialExtensionField<Val, 2>;
+
+pub type Perm = Poseidon2<Val, Poseidon2ExternalMatrixGeneral, DiffusionMatrixGoldilocks, 8, 7>;
+pub type Poseidon2Hash = PaddingFreeSponge<Perm, 8, 4, 4>;
+pub type Poseidon2Compress = TruncatedPermutation<Perm, 2, 4, 8>;
+pub type Poseidon2Mmcs = FieldMerkleTreeMmcs<
+    <Val as Field>::Packing,
+    <Val as Field>::Packing,
+    Poseidon2Hash,
+    Poseidon2Compress,
+    4,
+>;
+pub type Poseidon2Challenger = DuplexChallenger<Val, Perm, 8, 4>;
+pub type Poseidon2Config = StarkConfig<
+    TwoAdicFriPcs<Val, Dft, Poseidon2Mmcs, ExtensionMmcs<Val, Challenge, Poseidon2Mmcs>>,
+    Challenge,
+    Poseidon2Challenger,
+>;
+
+/// Merkle trees and challenger over a 32-byte hash `H` of the elements' canonical `u64`s.
+pub type ByteMmcs<H> = FieldMerkleTreeMmcs<
+    Val,
+    u8,
+    SerializingHasher64<H>,
+    CompressionFunctionFromHasher<u8, H, 2, 32>,
+    32,
+>;
+pub type ByteChallenger<H> = SerializingChallenger64<Val, HashChallenger<u8, H, 32>>;
+pub type ByteConfig<H> = StarkConfig<
+    TwoAdicFriPcs<Val, Dft, ByteMmcs<H>, ExtensionMmcs<Val, Challenge, ByteMmcs<H>>>,
+    Challenge,
+    ByteChallenger<H>,
+>;
+
+/// Round constants of the Goldilocks Poseidon2 permutation.
+pub type Poseidon2Constants = perm::Poseidon2Constants<Val, WIDTH>;
+
+impl Poseidon2Field for Val {
+    const FIELD: FieldId = FieldId::Goldilocks;
+    const SBOX_DEGREE: u64 = 7;
+}
+
+pub fn poseidon2_stark(perm: &Perm, dft: Dft, fri: &FriParams) -> Stark<Poseidon2Config> {
+    let val_mmcs = Poseidon2Mmcs::new(
+        Poseidon2Hash::new(perm.clone()),
+        Poseidon2Compress::new(perm.clone()),
+    );
+    Stark::from_mmcs(
+        val_mmcs,
+        Poseidon2Challenger::new(perm.clone()),
+        fri,
+        Val::TWO_ADICITY,
+        |mmcs, fri| TwoAdicFriPcs::new(dft, mmcs, fri),
+    )
+}
+
+pub fn byte_stark<H>(hash: H, dft: Dft, fri: &FriParams) -> Stark<ByteConfig<H>>
+where
+    H: CryptographicHasher<u8, [u8; 32]> + Copy,
+{
+    let val_mmcs = ByteMmcs::new(
+        SerializingHasher64::new(hash),
+        CompressionFunctionFromHasher::new(hash),
+    );
+    Stark::from_mmcs(
+        val_mmcs,
+        ByteChallenger::from_hasher(vec![], hash),
+        fri,
+        Val::TWO_ADICITY,
+        |mmcs, fri| TwoAdicFriPcs::new(dft, mmcs, fri),
+    )
+}
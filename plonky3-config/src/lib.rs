@@ -0,0 +1,23 @@
+//! The STARK configurations the plonky3 services can prove with, one per field, challenge
+//! extension and commitment hash, and the Poseidon2 constants they are built from. Each AIR crate
+//! turns the [`Stark`](stark::Stark) of the configuration a server picked into its prover
+//! through a [`StarkVisitor`](stark::StarkVisitor).
+
+use p3_fri::FriConfig;
+use prover_serve::fri::FriParams;
+
+pub mod babybear;
+pub mod dft;
+pub mod goldilocks;
+pub mod mersenne31;
+pub mod perm;
+pub mod stark;
+
+pub fn fri_config<M>(mmcs: M, params: &FriParams) -> FriConfig<M> {
+    FriConfig {
+        log_blowup: params.log_blowup,
+        num_queries: params.num_queries,
+        proof_of_work_bits: params.proof_of_work_bits,
+        mmcs,
+    }
+}
@@ -0,0 +1,112 @@
+//! A circle STARK over Mersenne31: the [`CirclePcs`] stands in for FRI over a two-adic subgroup,
+//! which Mersenne31 lacks. Challenges come from a degree-3 extension.
+
+use std::marker::PhantomData;
+
+use p3_challenger::{DuplexChallenger, HashChallenger, SerializingChallenger32};
+use p3_circle::CirclePcs;
+use p3_commit::ExtensionMmcs;
+use p3_field::extension::{BinomialExtensionField, ComplexExtendable};
+use p3_field::Field;
+use p3_fri::FriConfig;
+use p3_merkle_tree::FieldMerkleTreeMmcs;
+use p3_mersenne_31::{DiffusionMatrixMersenne31, Mersenne31};
+use p3_poseidon2::{Poseidon2, Poseidon2ExternalMatrixGeneral};
+use p3_symmetric::{
+    CompressionFunctionFromHasher, CryptographicHasher, PaddingFreeSponge, SerializingHasher32,
+    TruncatedPermutation,
+};
+use p3_uni_stark::StarkConfig;
+use prover_serve::field::FieldId;
+use prover_serve::fri::FriParams;
+
+use crate::perm::{self, Poseidon2Field};
+use crate::stark::Stark;
+
+const WIDTH: usize = 16;
+
+pub type Val = Mersenne31;
+pub type Challenge = BinomialExtensionField<Val, 3>;
+
+pub type Perm = Poseidon2<Val, Poseidon2ExternalMatrixGeneral, DiffusionMatrixMersenne31, 16, 5>;
+pub type Poseidon2Hash = PaddingFreeSponge<Perm, 16, 8, 8>;
+pub type Poseidon2Compress = TruncatedPermutation<Perm, 2, 8, 16>;
+pub type Poseidon2Mmcs = FieldMerkleTreeMmcs<
+    <Val as Field>::Packing,
+    <Val as Field>::Packing,
+    Poseidon2Hash,
+    Poseidon2Compress,
+    8,
+>;
+pub type Poseidon2Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+pub type Poseidon2Config = StarkConfig<
+    CirclePcs<Val, Poseidon2Mmcs, ExtensionMmcs<Val, Challenge, Poseidon2Mmcs>>,
+    Challenge,
+    Poseidon2Challenger,
+>;
+
+/// Merkle trees and challenger over a 32-byte hash `H` of the elements' canonical `u32`s.
+pub type ByteMmcs<H> = FieldMerkleTreeMmcs<
+    Val,
+    u8,
+    SerializingHasher32<H>,
+    CompressionFunctionFromHasher<u8, H, 2, 32>,
+    32,
+>;
+pub type ByteChallenger<H> = SerializingChallenger32<Val, HashChallenger<u8, H, 32>>;
+pub type ByteConfig<H> = StarkConfig<
+    CirclePcs<Val, ByteMmcs<H>, ExtensionMmcs<Val, Challenge, ByteMmcs<H>>>,
+    Challenge,
+    ByteChallenger<H>,
+>;
+
+/// Round constants of the Mersenne31 Poseidon2 permutation.
+pub type Poseidon2Constants = perm::Poseidon2Constants<Val, WIDTH>;
+
+impl Poseidon2Field for Val {
+    const FIELD: FieldId = FieldId::Mersenne31;
+    const SBOX_DEGREE: u64 = 5;
+}
+
+/// The circle PCS over `mmcs`, with FRI as `fri` configures it.
+fn circle_pcs<M>(
+    mmcs: M,
+    fri: FriConfig<ExtensionMmcs<Val, Challenge, M>>,
+) -> CirclePcs<Val, M, ExtensionMmcs<Val, Challenge, M>> {
+    CirclePcs {
+        mmcs,
+        fri_config: fri,
+        _phantom: PhantomData,
+    }
+}
+
+pub fn poseidon2_stark(perm: &Perm, fri: &FriParams) -> Stark<Poseidon2Config> {
+    let val_mmcs = Poseidon2Mmcs::new(
+        Poseidon2Hash::new(perm.clone()),
+        Poseidon2Compress::new(perm.clone()),
+    );
+    Stark::from_mmcs(
+        val_mmcs,
+        Poseidon2Challenger::new(perm.clone()),
+        fri,
+        Val::CIRCLE_TWO_ADICITY,
+        circle_pcs,
+    )
+}
+
+pub fn byte_stark<H>(hash: H, fri: &FriParams) -> Stark<ByteConfig<H>>
+where
+    H: CryptographicHasher<u8, [u8; 32]> + Copy,
+{
+    let val_mmcs = ByteMmcs::new(
+        SerializingHasher32::new(hash),
+        CompressionFunctionFromHasher::new(hash),
+    );
+    Stark::from_mmcs(
+        val_mmcs,
+        ByteChallenger::from_hasher(vec![], hash),
+        fri,
+        Val::CIRCLE_TWO_ADICITY,
+        circle_pcs,
+    )
+}
@@ -0,0 +1,165 @@
+use std::path::{Path, PathBuf};
+
+use p3_field::PrimeField64;
+use p3_poseidon2::{
+    poseidon2_round_numbers_128, DiffusionPermutation, MdsLightPermutation, Poseidon2,
+    Poseidon2ExternalMatrixGeneral,
+};
+use prover_serve::args::Flags;
+use prover_serve::field::FieldId;
+use rand::distributions::{Distribution, Standard};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::{babybear, goldilocks, mersenne31};
+
+/// Seed used when neither `--seed` nor `--constants` is given, so that the server and the
+/// examples agree on the permutation out of the box.
+pub const DEFAULT_PERM_SEED: u64 = 0;
+
+/// A field the configurations can commit with a Poseidon2 permutation over.
+pub trait Poseidon2Field: PrimeField64 {
+    /// Written before the constants in a `--constants` file.
+    const FIELD: FieldId;
+    const SBOX_DEGREE: u64;
+}
+
+/// Round constants of a `WIDTH`-element Poseidon2 permutation over `F`, used for hashing,
+/// compression and the challenger.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "F: Serialize, [F; WIDTH]: Serialize",
+    deserialize = "F: Deserialize<'de>, [F; WIDTH]: Deserialize<'de>"
+))]
+pub struct Poseidon2Constants<F, const WIDTH: usize> {
+    pub rounds_f: usize,
+    pub external: Vec<[F; WIDTH]>,
+    pub rounds_p: usize,
+    pub internal: Vec<F>,
+}
+
+impl<F: Poseidon2Field, const WIDTH: usize> Poseidon2Constants<F, WIDTH> {
+    /// Samples 128-bit-secure round constants from a ChaCha20 stream seeded with `seed`.
+    pub fn from_seed(seed: u64) -> Self
+    where
+        Standard: Distribution<F> + Distribution<[F; WIDTH]>,
+    {
+        let mut rng = ChaCha20Rng::seed_from_u64(seed);
+        let (rounds_f, rounds_p) = poseidon2_round_numbers_128::<F>(WIDTH, F::SBOX_DEGREE);
+        let external = (&mut rng).sample_iter(Standard).take(rounds_f).collect();
+        let internal = (&mut rng).sample_iter(Standard).take(rounds_p).collect();
+        Poseidon2Constants {
+            rounds_f,
+            external,
+            rounds_p,
+            internal,
+        }
+    }
+
+    pub fn to_perm<Diffusion, const D: u64>(
+        &self,
+    ) -> Poseidon2<F, Poseidon2ExternalMatrixGeneral, Diffusion, WIDTH, D>
+    where
+        Poseidon2ExternalMatrixGeneral: MdsLightPermutation<F, WIDTH>,
+        Diffusion: DiffusionPermutation<F, WIDTH> + Default,
+    {
+        Poseidon2::new(
+            self.rounds_f,
+            self.external.clone(),
+            Poseidon2ExternalMatrixGeneral,
+            self.rounds_p,
+            self.internal.clone(),
+            Diffusion::default(),
+        )
+    }
+
+    /// The constants as `--constants` reads them, after the field they are for.
+    pub fn to_file_bytes(&self) -> Vec<u8>
+    where
+        Self: Serialize,
+    {
+        postcard::to_allocvec(&(F::FIELD, self)).expect("unable to serialize constants")
+    }
+}
+
+/// Where the Poseidon2 constants come from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PermSource {
+    /// Constants derived from a fixed seed, see [`Poseidon2Constants::from_seed`].
+    Seed(u64),
+    /// Constants of the field's permutation stored on disk, as
+    /// [`Poseidon2Constants::to_file_bytes`] writes them.
+    File(PathBuf),
+}
+
+impl Default for PermSource {
+    fn default() -> Self {
+        PermSource::Seed(DEFAULT_PERM_SEED)
+    }
+}
+
+impl PermSource {
+    /// Takes the optional `--seed <u64>` or `--constants <path>` flags.
+    pub fn from_flags(flags: &mut Flags) -> Result<Self, String> {
+        match (flags.take("seed")?, flags.take("constants")?) {
+            (None, None) => Ok(PermSource::default()),
+            (Some(seed), None) => Ok(PermSource::Seed(seed)),
+            (None, Some(path)) => Ok(PermSource::File(path)),
+            (Some(_), Some(_)) => Err("--seed and --constants are mutually exclusive".to_string()),
+        }
+    }
+
+    pub fn constants(&self) -> Result<babybear::Poseidon2Constants, String> {
+        self.field_constants()
+    }
+
+    /// The constants of the Goldilocks permutation, for servers started with `--field goldilocks`.
+    pub fn goldilocks_constants(&self) -> Result<goldilocks::Poseidon2Constants, String> {
+        self.field_constants()
+    }
+
+    /// The constants of the Mersenne31 permutation, for servers started with `--field mersenne31`.
+    pub fn mersenne31_constants(&self) -> Result<mersenne31::Poseidon2Constants, String> {
+        self.field_constants()
+    }
+
+    fn field_constants<F, const WIDTH: usize>(&self) -> Result<Poseidon2Constants<F, WIDTH>, String>
+    where
+        F: Poseidon2Field,
+        Standard: Distribution<F> + Distribution<[F; WIDTH]>,
+        Poseidon2Constants<F, WIDTH>: DeserializeOwned,
+    {
+        match self {
+            PermSource::Seed(seed) => Ok(Poseidon2Constants::from_seed(*seed)),
+            PermSource::File(path) => read_constants::<F, _>(path),
+        }
+    }
+}
+
+/// Reads the constants of `F`'s permutation, refusing a file written for another field or with
+/// bytes left over after the constants.
+fn read_constants<F: Poseidon2Field, C: DeserializeOwned>(path: &Path) -> Result<C, String> {
+    let bytes =
+        std::fs::read(path).map_err(|e| format!("unable to read {}: {}", path.display(), e))?;
+    let deserialize_error = |e| format!("unable to deserialize {}: {:?}", path.display(), e);
+    let (field, rest) = postcard::take_from_bytes::<FieldId>(&bytes).map_err(deserialize_error)?;
+    if field != F::FIELD {
+        return Err(format!(
+            "{} holds {} constants, not {} ones",
+            path.display(),
+            field,
+            F::FIELD
+        ));
+    }
+    let (constants, rest) = postcard::take_from_bytes::<C>(rest).map_err(deserialize_error)?;
+    if !rest.is_empty() {
+        return Err(format!(
+            "{} has {} bytes after the constants",
+            path.display(),
+            rest.len()
+        ));
+    }
+    Ok(constants)
+}
@@ -0,0 +1,270 @@
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+use p3_blake3::Blake3;
+use p3_commit::ExtensionMmcs;
+use p3_field::{HasTwoAdicBionmialExtension, PrimeField64};
+use p3_fri::FriConfig;
+use p3_keccak::Keccak256Hash;
+use p3_uni_stark::{Proof, StarkConfig, StarkGenericConfig, Val, VerificationError};
+use prover_serve::backend::{VerifyError, VerifyErrorKind};
+use prover_serve::field::FieldId;
+use prover_serve::fri::FriParams;
+use prover_serve::hash::HashId;
+use prover_serve::preset::Preset;
+use prover_serve::proof::ProofConfig;
+use prover_serve::vk::keccak256;
+use serde::Serialize;
+
+use crate::dft::Dft;
+use crate::perm::PermSource;
+use crate::{babybear, fri_config, goldilocks, mersenne31};
+
+/// A STARK configuration and the challenger every proof and verification under it starts from.
+pub struct Stark<SC: StarkGenericConfig> {
+    pub config: SC,
+    challenger: SC::Challenger,
+    /// Log2 of the largest domain the PCS commits over, which bounds trace height times blowup.
+    pub max_log_domain: usize,
+}
+
+impl<SC: StarkGenericConfig> Stark<SC>
+where
+    SC::Challenger: Clone,
+{
+    pub fn new(config: SC, challenger: SC::Challenger, max_log_domain: usize) -> Self {
+        Stark {
+            config,
+            challenger,
+            max_log_domain,
+        }
+    }
+
+    pub fn challenger(&self) -> SC::Challenger {
+        self.challenger.clone()
+    }
+}
+
+impl<Pcs, Challenge, Challenger> Stark<StarkConfig<Pcs, Challenge, Challenger>>
+where
+    StarkConfig<Pcs, Challenge, Challenger>: StarkGenericConfig<Challenger = Challenger>,
+    Challenger: Clone,
+{
+    /// Commits to traces with `val_mmcs` and to challenges with Merkle trees of the same kind,
+    /// under the PCS `pcs` makes of them and FRI with `fri`. Every field's configurations are
+    /// built this way, whatever they hash with.
+    pub fn from_mmcs<F, M: Clone>(
+        val_mmcs: M,
+        challenger: Challenger,
+        fri: &FriParams,
+        max_log_domain: usize,
+        pcs: impl FnOnce(M, FriConfig<ExtensionMmcs<F, Challenge, M>>) -> Pcs,
+    ) -> Self {
+        let challenge_mmcs = ExtensionMmcs::new(val_mmcs.clone());
+        let pcs = pcs(val_mmcs, fri_config(challenge_mmcs, fri));
+        Stark::new(StarkConfig::new(pcs), challenger, max_log_domain)
+    }
+}
+
+/// Turns the [`Stark`] of whichever configuration a server picked into something that does not
+/// depend on its type, e.g. an AIR's prover behind a trait object.
+pub trait StarkVisitor {
+    type Output;
+
+    fn visit<SC>(self, stark: Stark<SC>) -> Self::Output
+    where
+        SC: StarkGenericConfig + Send + Sync + 'static,
+        SC::Challenger: Clone + Send + Sync,
+        Val<SC>: PrimeField64,
+        Proof<SC>: Send + Sync;
+}
+
+/// How the verifying key names the commitment hash of a configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HashKey {
+    pub name: String,
+    /// [`keccak256`] of the serialized Poseidon2 constants, zero for byte hashes.
+    pub constants_digest: [u8; 32],
+}
+
+impl HashKey {
+    fn poseidon2(name: &str, constants: &impl Serialize) -> Self {
+        let constants_serialized =
+            postcard::to_allocvec(constants).expect("unable to serialize constants");
+        HashKey {
+            name: name.to_string(),
+            constants_digest: keccak256(&constants_serialized),
+        }
+    }
+
+    fn bytes(hash: HashId) -> Self {
+        HashKey {
+            name: hash.to_string(),
+            constants_digest: [0; 32],
+        }
+    }
+}
+
+/// Builds the [`Stark`] of `config` for `visitor`, with Poseidon2 constants from `perm_source`
+/// when the hash is Poseidon2.
+pub fn build<V: StarkVisitor>(
+    config: &ProofConfig,
+    perm_source: &PermSource,
+    fri: &FriParams,
+    visitor: V,
+) -> Result<(V::Output, HashKey), String> {
+    let dft = Dft::from(config.dft.unwrap_or_default());
+    match (config.field, config.extension_degree) {
+        (FieldId::BabyBear, 4) => {
+            build_babybear::<V, 4>(config.hash, perm_source, dft, fri, visitor)
+        }
+        (FieldId::BabyBear, 5) => {
+            build_babybear::<V, 5>(config.hash, perm_source, dft, fri, visitor)
+        }
+        (FieldId::Goldilocks, 2) => build_goldilocks(config.hash, perm_source, dft, fri, visitor),
+        (FieldId::Mersenne31, 3) => build_mersenne31(config.hash, perm_source, fri, visitor),
+        (field, degree) => Err(format!("{} has no degree-{} extension", field, degree)),
+    }
+}
+
+/// The [`Stark`] of a server's own FRI parameters, and one for each preset prove requests may
+/// pick, as made into something typeless by a [`StarkVisitor`].
+pub struct Provers<T> {
+    default: T,
+    presets: BTreeMap<Preset, T>,
+}
+
+impl<T> Provers<T> {
+    /// Builds `config` with `fri` and with the parameters of each of `presets`.
+    pub fn build<V>(
+        config: &ProofConfig,
+        perm_source: &PermSource,
+        fri: &FriParams,
+        presets: &[Preset],
+        visitor: V,
+    ) -> Result<(Self, HashKey), String>
+    where
+        V: StarkVisitor<Output = T> + Copy,
+    {
+        let (default, hash) = build(config, perm_source, fri, visitor)?;
+        let presets = presets
+            .iter()
+            .map(|&preset| {
+                let (stark, _) = build(config, perm_source, &preset.fri_params(), visitor)?;
+                Ok((preset, stark))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok((Provers { default, presets }, hash))
+    }
+
+    /// The server's own for `None`, and an error for presets it does not allow.
+    pub fn get(&self, preset: Option<Preset>) -> Result<&T, String> {
+        match preset {
+            None => Ok(&self.default),
+            Some(preset) => self
+                .presets
+                .get(&preset)
+                .ok_or_else(|| format!("preset {} is not allowed", preset)),
+        }
+    }
+
+    pub fn presets(&self) -> Vec<Preset> {
+        self.presets.keys().copied().collect()
+    }
+}
+
+fn build_babybear<V: StarkVisitor, const D: usize>(
+    hash: HashId,
+    perm_source: &PermSource,
+    dft: Dft,
+    fri: &FriParams,
+    visitor: V,
+) -> Result<(V::Output, HashKey), String>
+where
+    babybear::Val: HasTwoAdicBionmialExtension<D>,
+{
+    let built = match hash {
+        HashId::Poseidon2 => {
+            let constants = perm_source.constants()?;
+            let stark = babybear::poseidon2_stark::<D>(&constants.to_perm(), dft, fri);
+            let key = HashKey::poseidon2("Poseidon2BabyBear16", &constants);
+            (visitor.visit(stark), key)
+        }
+        HashId::Keccak => (
+            visitor.visit(babybear::byte_stark::<_, D>(Keccak256Hash {}, dft, fri)),
+            HashKey::bytes(hash),
+        ),
+        HashId::Blake3 => (
+            visitor.visit(babybear::byte_stark::<_, D>(Blake3 {}, dft, fri)),
+            HashKey::bytes(hash),
+        ),
+    };
+    Ok(built)
+}
+
+fn build_goldilocks<V: StarkVisitor>(
+    hash: HashId,
+    perm_source: &PermSource,
+    dft: Dft,
+    fri: &FriParams,
+    visitor: V,
+) -> Result<(V::Output, HashKey), String> {
+    let built = match hash {
+        HashId::Poseidon2 => {
+            let constants = perm_source.goldilocks_constants()?;
+            let stark = goldilocks::poseidon2_stark(&constants.to_perm(), dft, fri);
+            let key = HashKey::poseidon2("Poseidon2Goldilocks8", &constants);
+            (visitor.visit(stark), key)
+        }
+        HashId::Keccak => (
+            visitor.visit(goldilocks::byte_stark(Keccak256Hash {}, dft, fri)),
+            HashKey::bytes(hash),
+        ),
+        HashId::Blake3 => (
+            visitor.visit(goldilocks::byte_stark(Blake3 {}, dft, fri)),
+            HashKey::bytes(hash),
+        ),
+    };
+    Ok(built)
+}
+
+fn build_mersenne31<V: StarkVisitor>(
+    hash: HashId,
+    perm_source: &PermSource,
+    fri: &FriParams,
+    visitor: V,
+) -> Result<(V::Output, HashKey), String> {
+    let built = match hash {
+        HashId::Poseidon2 => {
+            let constants = perm_source.mersenne31_constants()?;
+            let stark = mersenne31::poseidon2_stark(&constants.to_perm(), fri);
+            let key = HashKey::poseidon2("Poseidon2Mersenne31_16", &constants);
+            (visitor.visit(stark), key)
+        }
+        HashId::Keccak => (
+            visitor.visit(mersenne31::byte_stark(Keccak256Hash {}, fri)),
+            HashKey::bytes(hash),
+        ),
+        HashId::Blake3 => (
+            visitor.visit(mersenne31::byte_stark(Blake3 {}, fri)),
+            HashKey::bytes(hash),
+        ),
+    };
+    Ok(built)
+}
+
+/// Reports why `p3_uni_stark::verify` rejected a proof.
+pub fn verify_error<E: Debug>(e: VerificationError<E>) -> VerifyError {
+    match e {
+        VerificationError::InvalidProofShape => {
+            VerifyError::new(VerifyErrorKind::ProofShape, "invalid proof shape")
+        }
+        VerificationError::InvalidOpeningArgument(e) => {
+            VerifyError::new(VerifyErrorKind::FriFailure, format!("{:?}", e))
+        }
+        VerificationError::OodEvaluationMismatch => VerifyError::new(
+            VerifyErrorKind::OodMismatch,
+            "out-of-domain evaluation mismatch",
+        ),
+    }
+}
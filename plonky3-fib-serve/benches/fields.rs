@@ -0,0 +1,50 @@
+//! The same Fibonacci witnesses proven over BabyBear with FRI, the default, over Goldilocks with
+//! FRI and over Mersenne31 with the circle PCS, each through the backend `/prove` uses.
+
+use std::collections::HashMap;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use plonky3_config::perm::PermSource;
+use plonky3_fib_serve::air::DEFAULT_FRI_PARAMS;
+use plonky3_fib_serve::backend::{FibBackend, DEFAULT_HASH};
+use plonky3_fib_serve::witness::FibonacciParams;
+use prover_serve::backend::ProofBackend;
+use prover_serve::field::FieldId;
+use prover_serve::proof::ProofConfig;
+
+fn fields(c: &mut Criterion) {
+    let perm_source = PermSource::default();
+    let backends = [
+        ("babybear_fri", FieldId::BabyBear),
+        ("goldilocks_fri", FieldId::Goldilocks),
+        ("mersenne31_circle", FieldId::Mersenne31),
+    ]
+    .map(|(name, field)| {
+        let config = ProofConfig::new(field, DEFAULT_HASH);
+        let backend = FibBackend::new(config, &perm_source, DEFAULT_FRI_PARAMS, &[]).unwrap();
+        (name, backend)
+    });
+
+    let mut group = c.benchmark_group("fields");
+    group.sample_size(10);
+    for log_n in [10, 14, 18] {
+        let params = serde_json::to_vec(&FibonacciParams { a: 0, b: 1, log_n }).unwrap();
+        for (name, backend) in &backends {
+            // the sequence wraps around differently in every field
+            let (witness, _) = backend
+                .generate("fibonacci", &HashMap::new(), &params)
+                .expect("valid parameters");
+            group.bench_with_input(BenchmarkId::new(*name, log_n), &witness, |b, witness| {
+                b.iter_batched(
+                    || witness.clone(),
+                    |witness| backend.prove(witness).expect("valid witness"),
+                    BatchSize::LargeInput,
+                )
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, fields);
+criterion_main!(benches);
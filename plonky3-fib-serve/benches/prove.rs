@@ -0,0 +1,82 @@
+//! Small proofs, where building the STARK configuration used to be a noticeable share of every
+//! request: `rebuilt_config` is what `/prove` did before the configuration was cached, checking
+//! the witness, building the configuration and challenger from the permutation, proving and
+//! serializing, and `cached_config` is what it does now.
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use p3_field::TwoAdicField;
+use plonky3_config::babybear::{self, Val};
+use plonky3_config::dft::Dft;
+use plonky3_config::perm::PermSource;
+use plonky3_fib_serve::air::{FibonacciAir, DEFAULT_FRI_PARAMS};
+use plonky3_fib_serve::backend::FibBackend;
+use plonky3_fib_serve::check::check_witness;
+use plonky3_fib_serve::witness::FibonacciParams;
+use prover_serve::backend::ProofBackend;
+use prover_serve::field::FieldId;
+use prover_serve::hash::HashId;
+use prover_serve::proof::ProofConfig;
+
+fn small_proofs(c: &mut Criterion) {
+    let perm_source = PermSource::default();
+    let perm = perm_source.constants().unwrap().to_perm();
+    let config = ProofConfig::new(FieldId::BabyBear, HashId::Poseidon2);
+    let backend = FibBackend::new(config, &perm_source, DEFAULT_FRI_PARAMS, &[]).unwrap();
+
+    c.bench_function("stark_config", |b| {
+        b.iter(|| babybear::poseidon2_stark::<4>(&perm, Dft::default(), &DEFAULT_FRI_PARAMS))
+    });
+
+    let mut group = c.benchmark_group("prove");
+    for log_n in [3, 5, 7] {
+        let params = FibonacciParams { a: 0, b: 1, log_n };
+        let witness = params
+            .witness::<Val>(DEFAULT_FRI_PARAMS.log_blowup, Val::TWO_ADICITY)
+            .expect("valid parameters");
+        group.bench_with_input(
+            BenchmarkId::new("cached_config", log_n),
+            &witness,
+            |b, witness| {
+                b.iter_batched(
+                    || witness.clone(),
+                    |witness| backend.prove(witness).expect("valid witness"),
+                    BatchSize::SmallInput,
+                )
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("rebuilt_config", log_n),
+            &witness,
+            |b, witness| {
+                b.iter_batched(
+                    || witness.clone(),
+                    |witness| {
+                        let (trace, pis) = witness
+                            .to_field::<Val>(DEFAULT_FRI_PARAMS.log_blowup, Val::TWO_ADICITY)
+                            .expect("valid witness");
+                        check_witness(&trace, &pis).expect("valid witness");
+                        let stark = babybear::poseidon2_stark::<4>(
+                            &perm,
+                            Dft::default(),
+                            &DEFAULT_FRI_PARAMS,
+                        );
+                        let mut challenger = stark.challenger();
+                        let proof = p3_uni_stark::prove(
+                            &stark.config,
+                            &FibonacciAir {},
+                            &mut challenger,
+                            trace,
+                            &pis,
+                        );
+                        postcard::to_allocvec(&proof).expect("unable to serialize proof")
+                    },
+                    BatchSize::SmallInput,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, small_proofs);
+criterion_main!(benches);
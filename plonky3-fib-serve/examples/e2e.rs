@@ -1,62 +1,81 @@
-use p3_baby_bear::{BabyBear, DiffusionMatrixBabyBear};
-use p3_field::AbstractField;
-use p3_fri::FriConfig;
-use p3_poseidon2::Poseidon2ExternalMatrixGeneral;
-use p3_uni_stark::{prove, verify};
+use p3_baby_bear::BabyBear;
+use p3_field::PrimeField64;
+use p3_matrix::dense::RowMajorMatrix;
+use plonky3_config::perm::PermSource;
 use plonky3_fib_serve::air::*;
-
-use rand::thread_rng;
+use plonky3_fib_serve::backend::{FibBackend, DEFAULT_HASH};
+use prover_serve::args::Flags;
+use prover_serve::backend::ProofBackend;
+use prover_serve::field::FieldId;
+use prover_serve::fri::FriOverrides;
+use prover_serve::hash::HashId;
+use prover_serve::preset::Preset;
+use prover_serve::proof::ProofConfig;
 
 const WITNESS_LOC: &str = "../example_witness.bin";
 const PIS_LOC: &str = "../example_pis.bin";
 const PROOF_LOC: &str = "../example_proof.bin";
+const CONSTANTS_LOC: &str = "../example_poseidon2_constants.bin";
 
 fn main() {
-    // circuit-agnostic setup
-    let perm = Perm::new_from_rng_128(
-        Poseidon2ExternalMatrixGeneral,
-        DiffusionMatrixBabyBear::default(),
-        &mut thread_rng(),
-    );
-    let hash = MyHash::new(perm.clone());
-    let compress = MyCompress::new(perm.clone());
-    let val_mmcs = ValMmcs::new(hash, compress);
-    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
-    let dft = Dft {};
-    let fri_config = FriConfig {
-        log_blowup: 2,
-        num_queries: 28,
-        proof_of_work_bits: 8,
-        mmcs: challenge_mmcs,
-    };
-    let pcs = Pcs::new(dft, val_mmcs, fri_config);
-    let config = MyConfig::new(pcs);
-    let mut challenger = Challenger::new(perm.clone());
+    // circuit-agnostic setup, accepts the same `--field`, `--hash`, `--extension-degree`, `--dft`,
+    // `--seed` / `--constants` and FRI flags as the server, and `--preset <name>` to prove with
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let mut flags = Flags::parse(&args).expect("invalid arguments");
+    let config = ProofConfig::from_flags(&mut flags, DEFAULT_HASH).expect("invalid arguments");
+    let perm_source = PermSource::from_flags(&mut flags).expect("invalid arguments");
+    let preset = flags.take::<Preset>("preset").expect("invalid arguments");
+    let fri = FriOverrides::from_flags(&mut flags)
+        .and_then(|overrides| overrides.apply(DEFAULT_FRI_PARAMS))
+        .expect("invalid arguments");
+    flags.finish().expect("invalid arguments");
+    // same prover and verifier as the service
+    let backend = FibBackend::new(config, &perm_source, fri, preset.as_slice())
+        .expect("unable to set up the prover");
+    if config.hash == HashId::Poseidon2 {
+        let constants_serialized = match config.field {
+            FieldId::BabyBear => perm_source.constants().map(|c| c.to_file_bytes()),
+            FieldId::Goldilocks => perm_source
+                .goldilocks_constants()
+                .map(|c| c.to_file_bytes()),
+            FieldId::Mersenne31 => perm_source
+                .mersenne31_constants()
+                .map(|c| c.to_file_bytes()),
+        }
+        .expect("unable to load poseidon2 constants");
+        std::fs::write(CONSTANTS_LOC, &constants_serialized)
+            .expect("unable to write constants to file");
+    }
 
-    // execution trace
-    let trace = generate_trace_rows::<Val>(0, 1, 1 << 3);
+    // execution trace, as canonical values, which are the same over every field
+    let trace = generate_trace_rows::<BabyBear>(0, 1, 1 << 3);
     println!("trace (2X8): {:?}", trace);
-    let pis = vec![
-        BabyBear::from_canonical_u64(0),
-        BabyBear::from_canonical_u64(1),
-        BabyBear::from_canonical_u64(21),
-    ];
+    let values = trace
+        .values
+        .iter()
+        .map(BabyBear::as_canonical_u64)
+        .collect();
+    let trace = RowMajorMatrix::new(values, trace.width);
+    let pis = FibPublicValues::new(vec![0, 1, 21]);
 
     // witness: trace and public value
     let pis_serialized = postcard::to_allocvec(&pis.clone()).expect("unable to serialize pis");
     std::fs::write(PIS_LOC, &pis_serialized).expect("unable to write pis to file");
-    let witness = MyWitness { trace, pis };
+    let witness = MyWitness::new(trace, pis.values);
     let witness_serialized = postcard::to_allocvec(&witness).expect("unable to serialize witness");
     std::fs::write(WITNESS_LOC, &witness_serialized).expect("unable to write witness to file");
     let witness: MyWitness =
         postcard::from_bytes(&witness_serialized).expect("unable to deserialize witness");
-    let (trace, pis) = (witness.trace, witness.pis);
+    let pis = FibPublicValues::new(witness.pis.clone());
 
-    let proof = prove(&config, &FibonacciAir {}, &mut challenger, trace, &pis);
+    let proof = match preset {
+        Some(preset) => backend.prove_preset(witness, preset),
+        None => backend.prove(witness),
+    }
+    .expect("unable to prove witness");
     let proof_serialized = postcard::to_allocvec(&proof).expect("unable to serialize proof");
     std::fs::write(PROOF_LOC, &proof_serialized).expect("unable to write proof to file");
 
-    let mut challenger = Challenger::new(perm);
-    verify(&config, &FibonacciAir {}, &mut challenger, &proof, &pis).expect("verification failed");
+    backend.verify(&pis, &proof).expect("verification failed");
     println!("verification successful");
 }
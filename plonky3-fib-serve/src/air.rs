@@ -1,20 +1,12 @@
 // Modified from: https://github.com/Plonky3/Plonky3/blob/a650e8c5b1e800de7fce43b4e9b22a41e36eaa03/uni-stark/tests/fib_air.rs
 
 use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir};
-use p3_baby_bear::{BabyBear, DiffusionMatrixBabyBear};
-use p3_challenger::DuplexChallenger;
-use p3_commit::ExtensionMmcs;
-use p3_dft::Radix2DitParallel;
-use p3_field::extension::BinomialExtensionField;
-use p3_field::{Field, PrimeField64};
-use p3_fri::TwoAdicFriPcs;
+use p3_field::PrimeField64;
 use p3_matrix::dense::RowMajorMatrix;
 use p3_matrix::Matrix;
-use p3_merkle_tree::FieldMerkleTreeMmcs;
-use p3_poseidon2::{Poseidon2, Poseidon2ExternalMatrixGeneral};
-use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
-use p3_uni_stark::StarkConfig;
-use serde::{Deserialize, Serialize};
+use prover_serve::fri::FriParams;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::borrow::Borrow;
 
 /// For testing the public values feature
@@ -78,7 +70,9 @@ pub fn generate_trace_rows<F: PrimeField64>(a: u64, b: u64, n: usize) -> RowMajo
     trace
 }
 
-const NUM_FIBONACCI_COLS: usize = 2;
+pub const NUM_FIBONACCI_COLS: usize = 2;
+/// a, b and the last value x, which `eval` reads.
+pub const NUM_PUBLIC_VALUES: usize = 3;
 
 pub struct FibonacciRow<F> {
     pub left: F,
@@ -102,21 +96,206 @@ impl<F> Borrow<FibonacciRow<F>> for [F] {
     }
 }
 
-pub type Val = BabyBear;
-pub type Perm = Poseidon2<Val, Poseidon2ExternalMatrixGeneral, DiffusionMatrixBabyBear, 16, 7>;
-pub type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
-pub type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
-pub type ValMmcs =
-    FieldMerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
-pub type Challenge = BinomialExtensionField<Val, 4>;
-pub type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
-pub type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
-pub type Dft = Radix2DitParallel;
-pub type Pcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
-pub type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
+/// Leads [`MyWitness`] and [`FibPublicValues`]. Bodies of BabyBear elements, from before values
+/// were canonical, start with a varint vector length instead, which never reads `0x80 0x00`, so
+/// they fail to decode rather than decode as other values.
+const CANONICAL_FORMAT: [u8; 4] = [0x80, 0x00, b'c', 1];
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CanonicalFormat;
+
+impl Serialize for CanonicalFormat {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        CANONICAL_FORMAT.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for CanonicalFormat {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if <[u8; 4]>::deserialize(deserializer)? == CANONICAL_FORMAT {
+            Ok(CanonicalFormat)
+        } else {
+            Err(D::Error::custom(
+                "not canonical values, BabyBear elements are no longer accepted",
+            ))
+        }
+    }
+}
+
+/// Body of `POST /prove`, with canonical values so that one witness serves every field that has
+/// room for them.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MyWitness {
-    pub trace: RowMajorMatrix<Val>,
-    pub pis: Vec<BabyBear>,
+    pub format: CanonicalFormat,
+    pub trace: RowMajorMatrix<u64>,
+    pub pis: Vec<u64>,
+}
+
+/// Public inputs of `POST /verify`: a, b and x as canonical values.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FibPublicValues {
+    pub format: CanonicalFormat,
+    pub values: Vec<u64>,
+}
+
+impl FibPublicValues {
+    pub fn new(values: Vec<u64>) -> Self {
+        FibPublicValues {
+            format: CanonicalFormat,
+            values,
+        }
+    }
+}
+
+impl MyWitness {
+    pub fn new(trace: RowMajorMatrix<u64>, pis: Vec<u64>) -> Self {
+        MyWitness {
+            format: CanonicalFormat,
+            trace,
+            pis,
+        }
+    }
+
+    /// The trace and public values as elements of `F`, failing on values at or above its order,
+    /// on values that do not make whole rows, and on traces too tall to fit, blown up, a domain
+    /// of `2^max_log_domain` points.
+    pub fn to_field<F: PrimeField64>(
+        &self,
+        log_blowup: usize,
+        max_log_domain: usize,
+    ) -> Result<(RowMajorMatrix<F>, Vec<F>), String> {
+        let (width, len) = (self.trace.width, self.trace.values.len());
+        if width == 0 || !len.is_multiple_of(width) {
+            return Err(format!(
+                "{} trace values do not make rows of {} columns",
+                len, width
+            ));
+        }
+        let height = len / width;
+        let max_log_height = max_log_domain.saturating_sub(log_blowup);
+        if height > 1 << max_log_height {
+            return Err(format!(
+                "trace has {} rows, above the maximum of 2^{}",
+                height, max_log_height
+            ));
+        }
+        let values = to_field(&self.trace.values)?;
+        let trace = RowMajorMatrix::new(values, self.trace.width);
+        Ok((trace, to_field(&self.pis)?))
+    }
+}
+
+/// Maps canonical values into `F`, failing on the first one at or above its order.
+pub fn to_field<F: PrimeField64>(values: &[u64]) -> Result<Vec<F>, String> {
+    values
+        .iter()
+        .map(|&value| {
+            if value < F::ORDER_U64 {
+                Ok(F::from_canonical_u64(value))
+            } else {
+                Err(format!(
+                    "{} is not below the field order {}",
+                    value,
+                    F::ORDER_U64
+                ))
+            }
+        })
+        .collect()
+}
+
+pub const DEFAULT_FRI_PARAMS: FriParams = FriParams {
+    log_blowup: 2,
+    num_queries: 28,
+    proof_of_work_bits: 8,
+};
+
+#[cfg(test)]
+mod tests {
+    use p3_baby_bear::BabyBear;
+    use p3_field::{AbstractField, TwoAdicField};
+
+    use super::*;
+
+    /// The witness as it was serialized before values were canonical.
+    #[derive(Serialize)]
+    struct BabyBearWitness {
+        trace: RowMajorMatrix<BabyBear>,
+        pis: Vec<BabyBear>,
+    }
+
+    fn babybear_witness() -> BabyBearWitness {
+        let values = (0..8u32).map(BabyBear::from_canonical_u32).collect();
+        BabyBearWitness {
+            trace: RowMajorMatrix::new(values, NUM_FIBONACCI_COLS),
+            pis: vec![
+                BabyBear::zero(),
+                BabyBear::one(),
+                BabyBear::from_canonical_u32(7),
+            ],
+        }
+    }
+
+    fn canonical(values: &[BabyBear]) -> Vec<u64> {
+        values
+            .iter()
+            .map(|value| value.as_canonical_u64())
+            .collect()
+    }
+
+    #[test]
+    fn canonical_witness_round_trips_into_babybear() {
+        let old = babybear_witness();
+        let witness = MyWitness::new(
+            RowMajorMatrix::new(canonical(&old.trace.values), old.trace.width),
+            canonical(&old.pis),
+        );
+        let bytes = postcard::to_allocvec(&witness).unwrap();
+        let decoded = postcard::from_bytes::<MyWitness>(&bytes).unwrap();
+        assert_eq!(decoded, witness);
+        let (trace, pis) = decoded
+            .to_field::<BabyBear>(DEFAULT_FRI_PARAMS.log_blowup, BabyBear::TWO_ADICITY)
+            .unwrap();
+        assert_eq!(trace.values, old.trace.values);
+        assert_eq!(trace.width, old.trace.width);
+        assert_eq!(pis, old.pis);
+    }
+
+    #[test]
+    fn misshapen_traces_are_rejected() {
+        // built by hand, as `RowMajorMatrix::new` checks the shape itself
+        let witness = |values: Vec<u64>, width| {
+            MyWitness::new(RowMajorMatrix { values, width }, vec![0, 1, 1])
+        };
+        let shape = |witness: MyWitness, log_blowup, max_log_domain| {
+            witness
+                .to_field::<BabyBear>(log_blowup, max_log_domain)
+                .map(|_| ())
+        };
+        assert_eq!(
+            shape(witness(vec![0; 7], 2), 1, BabyBear::TWO_ADICITY),
+            Err("7 trace values do not make rows of 2 columns".to_string())
+        );
+        assert!(shape(witness(vec![], 0), 1, BabyBear::TWO_ADICITY).is_err());
+        assert_eq!(
+            shape(witness(vec![0; 16], 2), 1, 3),
+            Err("trace has 8 rows, above the maximum of 2^2".to_string())
+        );
+        assert_eq!(shape(witness(vec![0; 16], 2), 1, 4), Ok(()));
+    }
+
+    #[test]
+    fn babybear_serialized_bodies_do_not_decode() {
+        let old = babybear_witness();
+        let bytes = postcard::to_allocvec(&old).unwrap();
+        assert!(postcard::from_bytes::<MyWitness>(&bytes).is_err());
+        let bytes = postcard::to_allocvec(&old.pis).unwrap();
+        assert!(postcard::from_bytes::<FibPublicValues>(&bytes).is_err());
+
+        let pis = FibPublicValues::new(canonical(&old.pis));
+        let bytes = postcard::to_allocvec(&pis).unwrap();
+        assert_eq!(
+            postcard::from_bytes::<FibPublicValues>(&bytes).unwrap(),
+            pis
+        );
+    }
 }
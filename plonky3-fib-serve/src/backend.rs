@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+
+use p3_field::PrimeField64;
+use p3_uni_stark::{Proof, StarkGenericConfig, Val};
+use plonky3_config::perm::PermSource;
+use plonky3_config::stark::{verify_error, Provers, Stark, StarkVisitor};
+use prover_serve::backend::{ProofBackend, VerifyError, VerifyErrorKind};
+use prover_serve::fri::FriParams;
+use prover_serve::hash::HashId;
+use prover_serve::metrics::{self, Phase};
+use prover_serve::preset::Preset;
+use prover_serve::proof::{ProofBody, ProofConfig, TaggedProof};
+use prover_serve::vk::VerifyingKey;
+
+use crate::air::*;
+use crate::check::check_witness;
+use crate::witness::FibonacciParams;
+
+const AIR: &str = "FibonacciAir";
+
+/// Commitments use Poseidon2 unless `--hash` says otherwise.
+pub const DEFAULT_HASH: HashId = HashId::Poseidon2;
+
+/// Proves and verifies `FibonacciAir` under one [`Stark`], whatever its configuration type.
+trait FibStark: Send + Sync {
+    fn prove(&self, witness: MyWitness, log_blowup: usize) -> Result<ProofBody, String>;
+
+    fn verify(&self, pis: &[u64], proof: &[u8]) -> Result<(), VerifyError>;
+
+    fn witness(&self, params: &FibonacciParams, log_blowup: usize) -> Result<MyWitness, String>;
+}
+
+impl<SC> FibStark for Stark<SC>
+where
+    SC: StarkGenericConfig + Send + Sync + 'static,
+    SC::Challenger: Clone + Send + Sync,
+    Val<SC>: PrimeField64,
+    Proof<SC>: Send + Sync,
+{
+    fn prove(&self, witness: MyWitness, log_blowup: usize) -> Result<ProofBody, String> {
+        let (trace, pis) = metrics::time(AIR, Phase::TraceGeneration, || {
+            let (trace, pis) = witness.to_field::<Val<SC>>(log_blowup, self.max_log_domain)?;
+            check_witness(&trace, &pis)?;
+            Ok::<_, String>((trace, pis))
+        })?;
+        let mut challenger = self.challenger();
+        let proof = metrics::time(AIR, Phase::Proving, || {
+            p3_uni_stark::prove(&self.config, &FibonacciAir {}, &mut challenger, trace, &pis)
+        });
+        Ok(ProofBody::new(proof))
+    }
+
+    fn verify(&self, pis: &[u64], proof: &[u8]) -> Result<(), VerifyError> {
+        let pis = to_field::<Val<SC>>(pis)
+            .map_err(|e| VerifyError::new(VerifyErrorKind::InvalidPublicInputs, e))?;
+        let proof = postcard::from_bytes::<Proof<SC>>(proof)
+            .map_err(|e| VerifyError::new(VerifyErrorKind::ProofDecode, e.to_string()))?;
+        let mut challenger = self.challenger();
+        p3_uni_stark::verify(
+            &self.config,
+            &FibonacciAir {},
+            &mut challenger,
+            &proof,
+            &pis,
+        )
+        .map_err(verify_error)
+    }
+
+    fn witness(&self, params: &FibonacciParams, log_blowup: usize) -> Result<MyWitness, String> {
+        params.witness::<Val<SC>>(log_blowup, self.max_log_domain)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Boxed;
+
+impl StarkVisitor for Boxed {
+    type Output = Box<dyn FibStark>;
+
+    fn visit<SC>(self, stark: Stark<SC>) -> Box<dyn FibStark>
+    where
+        SC: StarkGenericConfig + Send + Sync + 'static,
+        SC::Challenger: Clone + Send + Sync,
+        Val<SC>: PrimeField64,
+        Proof<SC>: Send + Sync,
+    {
+        Box::new(stark)
+    }
+}
+
+pub struct FibBackend {
+    /// Built once and shared by every proof and verification.
+    provers: Provers<Box<dyn FibStark>>,
+    config: ProofConfig,
+    fri: FriParams,
+    vk: VerifyingKey,
+}
+
+impl FibBackend {
+    /// A backend proving under `config`, with Poseidon2 constants from `perm_source` if it
+    /// commits with Poseidon2, and with `fri` unless a request picks one of `presets`.
+    pub fn new(
+        config: ProofConfig,
+        perm_source: &PermSource,
+        fri: FriParams,
+        presets: &[Preset],
+    ) -> Result<Self, String> {
+        let (provers, hash) = Provers::build(&config, perm_source, &fri, presets, Boxed)?;
+        let vk = VerifyingKey {
+            air: AIR.to_string(),
+            field: config.field.to_string(),
+            extension_degree: config.extension_degree as u64,
+            trace_width: NUM_FIBONACCI_COLS as u64,
+            log_blowup: fri.log_blowup as u64,
+            num_queries: fri.num_queries as u64,
+            proof_of_work_bits: fri.proof_of_work_bits as u64,
+            hash: hash.name,
+            hash_constants_digest: hash.constants_digest,
+        };
+        Ok(FibBackend {
+            provers,
+            config,
+            fri,
+            vk,
+        })
+    }
+
+    fn prove_with(
+        &self,
+        witness: MyWitness,
+        preset: Option<Preset>,
+    ) -> Result<TaggedProof, String> {
+        let log_blowup = preset
+            .map_or(self.fri, |preset| preset.fri_params())
+            .log_blowup;
+        Ok(TaggedProof {
+            config: self.config,
+            preset,
+            proof: self.provers.get(preset)?.prove(witness, log_blowup)?,
+        })
+    }
+}
+
+impl ProofBackend for FibBackend {
+    type Witness = MyWitness;
+    type PublicInputs = FibPublicValues;
+    type Proof = TaggedProof;
+
+    const WITNESS_FORMATS: &'static [&'static str] = &["fibonacci"];
+
+    fn prove(&self, witness: MyWitness) -> Result<TaggedProof, String> {
+        self.prove_with(witness, None)
+    }
+
+    fn prove_preset(&self, witness: MyWitness, preset: Preset) -> Result<TaggedProof, String> {
+        self.prove_with(witness, Some(preset))
+    }
+
+    fn presets(&self) -> Vec<Preset> {
+        self.provers.presets()
+    }
+
+    fn verify(&self, pis: &FibPublicValues, proof: &TaggedProof) -> Result<(), VerifyError> {
+        let pis = &pis.values;
+        if pis.len() != NUM_PUBLIC_VALUES {
+            return Err(VerifyError::new(
+                VerifyErrorKind::InvalidPublicInputs,
+                format!(
+                    "{} public values instead of {} (a, b, x)",
+                    pis.len(),
+                    NUM_PUBLIC_VALUES
+                ),
+            ));
+        }
+        let bytes = proof.check(&self.config)?;
+        let stark = self
+            .provers
+            .get(proof.preset)
+            .map_err(|e| VerifyError::new(VerifyErrorKind::PresetNotAllowed, e))?;
+        stark.verify(pis, &bytes)
+    }
+
+    fn verifying_key(&self) -> VerifyingKey {
+        self.vk.clone()
+    }
+
+    fn generate(
+        &self,
+        _format: &str,
+        query: &HashMap<String, String>,
+        body: &[u8],
+    ) -> Result<(MyWitness, FibPublicValues), String> {
+        let params = serde_json::from_slice::<FibonacciParams>(body)
+            .map_err(|e| format!("invalid fibonacci parameters: {}", e))?;
+        // the trace has to fit the domain of the preset it will be proven with
+        let preset = query
+            .get("preset")
+            .map(|name| name.parse::<Preset>())
+            .transpose()?;
+        let log_blowup = preset
+            .map_or(self.fri, |preset| preset.fri_params())
+            .log_blowup;
+        let witness = metrics::time(AIR, Phase::TraceGeneration, || {
+            self.provers.get(preset)?.witness(&params, log_blowup)
+        })?;
+        let pis = FibPublicValues::new(witness.pis.clone());
+        Ok((witness, pis))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prover_serve::field::FieldId;
+
+    use super::*;
+
+    fn backend() -> FibBackend {
+        backend_with(&[])
+    }
+
+    fn backend_with(presets: &[Preset]) -> FibBackend {
+        let config = ProofConfig::new(FieldId::BabyBear, DEFAULT_HASH);
+        FibBackend::new(config, &PermSource::default(), DEFAULT_FRI_PARAMS, presets).unwrap()
+    }
+
+    fn fibonacci(log_n: usize) -> Vec<u8> {
+        let params = FibonacciParams { log_n, a: 0, b: 1 };
+        serde_json::to_vec(&params).unwrap()
+    }
+
+    #[test]
+    fn wrong_number_of_public_values_is_rejected() {
+        let backend = backend();
+        let (witness, pis) = backend
+            .generate("fibonacci", &HashMap::new(), &fibonacci(3))
+            .unwrap();
+        let proof = backend.prove(witness).unwrap();
+        backend.verify(&pis, &proof).unwrap();
+
+        let pis = pis.values;
+        for values in [vec![], pis[..2].to_vec(), [pis.as_slice(), &[0]].concat()] {
+            let error = backend
+                .verify(&FibPublicValues::new(values), &proof)
+                .unwrap_err();
+            assert_eq!(error.kind, VerifyErrorKind::InvalidPublicInputs);
+        }
+    }
+
+    #[test]
+    fn proofs_of_disallowed_presets_are_not_verified() {
+        let prover = backend_with(&[Preset::Fast]);
+        let query = HashMap::from([("preset".to_string(), "fast".to_string())]);
+        let (witness, pis) = prover.generate("fibonacci", &query, &fibonacci(3)).unwrap();
+        let proof = prover.prove_preset(witness, Preset::Fast).unwrap();
+        assert_eq!(proof.preset, Some(Preset::Fast));
+        prover.verify(&pis, &proof).unwrap();
+
+        let error = backend().verify(&pis, &proof).unwrap_err();
+        assert_eq!(error.kind, VerifyErrorKind::PresetNotAllowed);
+    }
+}
@@ -0,0 +1,210 @@
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir};
+use p3_field::Field;
+use p3_matrix::dense::{RowMajorMatrix, RowMajorMatrixView};
+use p3_matrix::stack::VerticalPair;
+use p3_matrix::Matrix;
+
+use crate::air::{FibonacciAir, NUM_FIBONACCI_COLS, NUM_PUBLIC_VALUES};
+
+/// `FibonacciAir` constraints, in the order `eval` asserts them.
+const FIBONACCI_CONSTRAINTS: [&str; 5] = [
+    "first row: left == pis[0]",
+    "first row: right == pis[1]",
+    "transition: next.left == right",
+    "transition: next.right == left + right",
+    "last row: right == pis[2]",
+];
+
+/// Checks that a witness has the shape `FibonacciAir` expects and satisfies its constraints over
+/// `F`, so that a bad witness is rejected before proving instead of yielding a proof that won't
+/// verify.
+pub fn check_witness<F: Field>(trace: &RowMajorMatrix<F>, pis: &[F]) -> Result<(), String> {
+    let (width, height) = (trace.width(), trace.height());
+    if width != NUM_FIBONACCI_COLS {
+        return Err(format!(
+            "trace has {} columns instead of {}",
+            width, NUM_FIBONACCI_COLS
+        ));
+    }
+    if !height.is_power_of_two() {
+        return Err(format!("trace has {} rows, not a power of two", height));
+    }
+    if pis.len() != NUM_PUBLIC_VALUES {
+        return Err(format!(
+            "{} public values instead of {} (a, b, x)",
+            pis.len(),
+            NUM_PUBLIC_VALUES
+        ));
+    }
+    check_constraints(&FibonacciAir {}, trace, pis, &FIBONACCI_CONSTRAINTS)
+}
+
+/// Evaluates `air` on every pair of consecutive rows, wrapping around at the end like the
+/// prover does, and names the first constraint that does not hold.
+fn check_constraints<F, A>(
+    air: &A,
+    trace: &RowMajorMatrix<F>,
+    public_values: &[F],
+    constraint_names: &[&str],
+) -> Result<(), String>
+where
+    F: Field,
+    A: BaseAir<F> + for<'a> Air<ConstraintChecker<'a, F>>,
+{
+    let height = trace.height();
+    for row in 0..height {
+        let local = trace.row_slice(row);
+        let next = trace.row_slice((row + 1) % height);
+        let mut checker = ConstraintChecker {
+            main: VerticalPair::new(
+                RowMajorMatrixView::new_row(&*local),
+                RowMajorMatrixView::new_row(&*next),
+            ),
+            public_values,
+            is_first_row: F::from_bool(row == 0),
+            is_last_row: F::from_bool(row == height - 1),
+            is_transition: F::from_bool(row != height - 1),
+            constraints: 0,
+            first_violation: None,
+        };
+        air.eval(&mut checker);
+        if let Some(constraint) = checker.first_violation {
+            let name = constraint_names
+                .get(constraint)
+                .copied()
+                .unwrap_or("unnamed constraint");
+            return Err(format!(
+                "constraint #{} ({}) violated on row {}",
+                constraint, name, row
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// An [`AirBuilder`] over one row and its successor that records which assertion fails first,
+/// counting assertions in the order the AIR makes them.
+pub struct ConstraintChecker<'a, F: Field> {
+    main: VerticalPair<RowMajorMatrixView<'a, F>, RowMajorMatrixView<'a, F>>,
+    public_values: &'a [F],
+    is_first_row: F,
+    is_last_row: F,
+    is_transition: F,
+    constraints: usize,
+    first_violation: Option<usize>,
+}
+
+impl<'a, F: Field> AirBuilder for ConstraintChecker<'a, F> {
+    type F = F;
+    type Expr = F;
+    type Var = F;
+    type M = VerticalPair<RowMajorMatrixView<'a, F>, RowMajorMatrixView<'a, F>>;
+
+    fn main(&self) -> Self::M {
+        self.main
+    }
+
+    fn is_first_row(&self) -> Self::Expr {
+        self.is_first_row
+    }
+
+    fn is_last_row(&self) -> Self::Expr {
+        self.is_last_row
+    }
+
+    fn is_transition_window(&self, size: usize) -> Self::Expr {
+        assert_eq!(size, 2, "only two-row windows are supported");
+        self.is_transition
+    }
+
+    fn assert_zero<I: Into<Self::Expr>>(&mut self, x: I) {
+        if x.into() != F::zero() && self.first_violation.is_none() {
+            self.first_violation = Some(self.constraints);
+        }
+        self.constraints += 1;
+    }
+}
+
+impl<'a, F: Field> AirBuilderWithPublicValues for ConstraintChecker<'a, F> {
+    type PublicVar = F;
+
+    fn public_values(&self) -> &[Self::PublicVar] {
+        self.public_values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p3_baby_bear::BabyBear;
+    use p3_field::AbstractField;
+
+    use super::*;
+    use crate::air::generate_trace_rows;
+
+    /// Eight rows from (0, 1), ending in 21.
+    fn witness() -> (RowMajorMatrix<BabyBear>, Vec<BabyBear>) {
+        let pis = [0, 1, 21].map(BabyBear::from_canonical_u32).to_vec();
+        (generate_trace_rows(0, 1, 8), pis)
+    }
+
+    fn set(trace: &mut RowMajorMatrix<BabyBear>, row: usize, col: usize, value: u32) {
+        trace.values[row * NUM_FIBONACCI_COLS + col] = BabyBear::from_canonical_u32(value);
+    }
+
+    #[test]
+    fn valid_witness_passes() {
+        let (trace, pis) = witness();
+        assert_eq!(check_witness(&trace, &pis), Ok(()));
+    }
+
+    #[test]
+    fn wrong_first_left_is_named() {
+        let (trace, mut pis) = witness();
+        pis[0] = BabyBear::from_canonical_u32(2);
+        assert_eq!(
+            check_witness(&trace, &pis).unwrap_err(),
+            "constraint #0 (first row: left == pis[0]) violated on row 0"
+        );
+    }
+
+    #[test]
+    fn wrong_first_right_is_named() {
+        let (trace, mut pis) = witness();
+        pis[1] = BabyBear::from_canonical_u32(2);
+        assert_eq!(
+            check_witness(&trace, &pis).unwrap_err(),
+            "constraint #1 (first row: right == pis[1]) violated on row 0"
+        );
+    }
+
+    #[test]
+    fn broken_left_transition_is_named() {
+        let (mut trace, pis) = witness();
+        set(&mut trace, 4, 0, 4);
+        assert_eq!(
+            check_witness(&trace, &pis).unwrap_err(),
+            "constraint #2 (transition: next.left == right) violated on row 3"
+        );
+    }
+
+    #[test]
+    fn broken_right_transition_is_named() {
+        let (mut trace, pis) = witness();
+        set(&mut trace, 6, 1, 14);
+        // the example in the readme
+        assert_eq!(
+            check_witness(&trace, &pis).unwrap_err(),
+            "constraint #3 (transition: next.right == left + right) violated on row 5"
+        );
+    }
+
+    #[test]
+    fn wrong_last_value_is_named() {
+        let (trace, mut pis) = witness();
+        pis[2] = BabyBear::from_canonical_u32(34);
+        assert_eq!(
+            check_witness(&trace, &pis).unwrap_err(),
+            "constraint #4 (last row: right == pis[2]) violated on row 7"
+        );
+    }
+}
@@ -1 +1,4 @@
 pub mod air;
+pub mod backend;
+pub mod check;
+pub mod witness;
@@ -0,0 +1,55 @@
+use p3_field::PrimeField64;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use serde::{Deserialize, Serialize};
+
+use crate::air::{generate_trace_rows, MyWitness};
+
+/// Largest `log_n` accepted by `POST /prove/fibonacci`, so that one request cannot hold a
+/// worker for minutes.
+pub const MAX_LOG_N: usize = 22;
+
+/// Body of `POST /prove/fibonacci`, as JSON: the sequence starting with `a, b` over `2^log_n`
+/// rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FibonacciParams {
+    pub a: u64,
+    pub b: u64,
+    pub log_n: usize,
+}
+
+impl FibonacciParams {
+    /// The trace over `F` and the public values `[a, b, x]`, with `x` the last value of the
+    /// sequence. The trace, blown up, has to fit a domain of at most `2^max_log_domain` points:
+    /// two-adicity for FRI, circle two-adicity for the circle PCS.
+    pub fn witness<F: PrimeField64>(
+        &self,
+        log_blowup: usize,
+        max_log_domain: usize,
+    ) -> Result<MyWitness, String> {
+        for (name, value) in [("a", self.a), ("b", self.b)] {
+            if value >= F::ORDER_U64 {
+                return Err(format!(
+                    "{} = {} is not below the field order {}",
+                    name,
+                    value,
+                    F::ORDER_U64
+                ));
+            }
+        }
+        if self.log_n > MAX_LOG_N || self.log_n + log_blowup > max_log_domain {
+            return Err(format!(
+                "log_n = {} is above the maximum of {}",
+                self.log_n,
+                MAX_LOG_N.min(max_log_domain.saturating_sub(log_blowup))
+            ));
+        }
+        let trace = generate_trace_rows::<F>(self.a, self.b, 1 << self.log_n);
+        let x = trace.row_slice(trace.height() - 1)[1];
+        let values = trace.values.iter().map(F::as_canonical_u64).collect();
+        Ok(MyWitness::new(
+            RowMajorMatrix::new(values, trace.width()),
+            vec![self.a, self.b, x.as_canonical_u64()],
+        ))
+    }
+}
@@ -0,0 +1,52 @@
+//! The same Keccak-256 hashes proven over BabyBear with FRI, the default, over Goldilocks with
+//! FRI and over Mersenne31 with the circle PCS, each through the backend `/prove` uses. 1365
+//! hashes of 64-byte chunks is the proof arena's workload.
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use plonky3_config::perm::PermSource;
+use plonky3_keccak_serve::backend::{KeccakBackend, DEFAULT_HASH};
+use plonky3_keccak_serve::witness::CHUNK_SIZE;
+use plonky3_keccak_serve::{MyWitness, DEFAULT_FRI_PARAMS};
+use prover_serve::backend::ProofBackend;
+use prover_serve::field::FieldId;
+use prover_serve::proof::ProofConfig;
+
+fn fields(c: &mut Criterion) {
+    let backends = [
+        ("babybear_fri", FieldId::BabyBear),
+        ("goldilocks_fri", FieldId::Goldilocks),
+        ("mersenne31_circle", FieldId::Mersenne31),
+    ]
+    .map(|(name, field)| {
+        let config = ProofConfig::new(field, DEFAULT_HASH);
+        let backend =
+            KeccakBackend::new(config, &PermSource::default(), DEFAULT_FRI_PARAMS, &[]).unwrap();
+        (name, backend)
+    });
+
+    let mut group = c.benchmark_group("fields");
+    group.sample_size(10);
+    for num_hashes in [64, 256, 1365] {
+        let input_bytes = (0..num_hashes * CHUNK_SIZE)
+            .map(|i| i as u8)
+            .collect::<Vec<u8>>();
+        let witness = MyWitness::from_chunks(&input_bytes);
+        for (name, backend) in &backends {
+            group.bench_with_input(
+                BenchmarkId::new(*name, num_hashes),
+                &witness,
+                |b, witness| {
+                    b.iter_batched(
+                        || witness.clone(),
+                        |witness| backend.prove(witness).expect("valid witness"),
+                        BatchSize::LargeInput,
+                    )
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, fields);
+criterion_main!(benches);
@@ -1,11 +1,14 @@
 // Modified from: https://github.com/Plonky3/Plonky3/blob/55832146c86e8e4d246bb9843da17f2159d212a5/keccak-air/examples/prove_baby_bear_keccak.rs
 
-use p3_fri::FriConfig;
-use p3_keccak::Keccak256Hash;
-use p3_keccak_air::{generate_trace_rows, KeccakAir};
-
-use p3_uni_stark::{prove, verify};
+use plonky3_config::perm::PermSource;
+use plonky3_keccak_serve::backend::{KeccakBackend, DEFAULT_HASH};
+use plonky3_keccak_serve::witness::{KeccakPublicValues, CHUNK_SIZE};
 use plonky3_keccak_serve::*;
+use prover_serve::args::Flags;
+use prover_serve::backend::ProofBackend;
+use prover_serve::fri::FriOverrides;
+use prover_serve::preset::Preset;
+use prover_serve::proof::ProofConfig;
 use rand::random;
 
 const WITNESS_LOC: &str = "../example_witness.bin";
@@ -13,42 +16,48 @@ const PIS_LOC: &str = "../example_pis.bin";
 const PROOF_LOC: &str = "../example_proof.bin";
 
 fn main() {
-    let byte_hash = ByteHash {};
-    let field_hash = FieldHash::new(Keccak256Hash {});
-    let compress = MyCompress::new(byte_hash);
-    let val_mmcs = ValMmcs::new(field_hash, compress);
-    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
-    let dft = Dft {};
-    let fri_config = FriConfig {
-        log_blowup: 1,
-        num_queries: 100,
-        proof_of_work_bits: 16,
-        mmcs: challenge_mmcs,
-    };
-    let pcs = Pcs::new(dft, val_mmcs, fri_config);
-    let config = MyConfig::new(pcs);
-
-    // random input
-    let inputs = (0..NUM_HASHES).map(|_| random()).collect::<Vec<_>>();
-    let pis = &inputs;
+    // accepts the same `--field`, `--hash`, `--extension-degree`, `--dft`, `--seed` / `--constants`
+    // and FRI flags as the server, `--preset <name>` to prove with and `--num-hashes <n>`
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let mut flags = Flags::parse(&args).expect("invalid arguments");
+    let num_hashes = flags
+        .take::<usize>("num-hashes")
+        .expect("invalid arguments")
+        .unwrap_or(NUM_HASHES);
+    let config = ProofConfig::from_flags(&mut flags, DEFAULT_HASH).expect("invalid arguments");
+    let perm_source = PermSource::from_flags(&mut flags).expect("invalid arguments");
+    let preset = flags.take::<Preset>("preset").expect("invalid arguments");
+    let fri = FriOverrides::from_flags(&mut flags)
+        .and_then(|overrides| overrides.apply(DEFAULT_FRI_PARAMS))
+        .expect("invalid arguments");
+    flags.finish().expect("invalid arguments");
+    // same prover and verifier as the service
+    let backend = KeccakBackend::new(config, &perm_source, fri, preset.as_slice())
+        .expect("unable to set up the prover");
+
+    // random input, hashed in 64-byte chunks like the proof arena does
+    let input_bytes = (0..num_hashes * CHUNK_SIZE)
+        .map(|_| random())
+        .collect::<Vec<u8>>();
+    let witness = MyWitness::from_chunks(&input_bytes);
+    let pis = KeccakPublicValues::new(&witness.inputs);
 
     // witness: trace and public value
-    let pis_serialized = postcard::to_allocvec(&pis.clone()).expect("unable to serialize pis");
+    let pis_serialized = postcard::to_allocvec(&pis).expect("unable to serialize pis");
     std::fs::write(PIS_LOC, &pis_serialized).expect("unable to write pis to file");
-    let witness = MyWitness { inputs };
     let witness_serialized = postcard::to_allocvec(&witness).expect("unable to serialize witness");
     std::fs::write(WITNESS_LOC, &witness_serialized).expect("unable to write witness to file");
     let witness: MyWitness =
         postcard::from_bytes(&witness_serialized).expect("unable to deserialize witness");
-    let inputs = witness.inputs;
 
-    let mut challenger = Challenger::from_hasher(vec![], byte_hash);
-    let trace = generate_trace_rows::<Val>(inputs.clone());
-    let proof = prove(&config, &KeccakAir {}, &mut challenger, trace, &vec![]);
+    let proof = match preset {
+        Some(preset) => backend.prove_preset(witness, preset),
+        None => backend.prove(witness),
+    }
+    .expect("unable to prove witness");
     let proof_serialized = postcard::to_allocvec(&proof).expect("unable to serialize proof");
     std::fs::write(PROOF_LOC, &proof_serialized).expect("unable to write proof to file");
 
-    let mut challenger = Challenger::from_hasher(vec![], byte_hash);
-    verify(&config, &KeccakAir {}, &mut challenger, &proof, &vec![]).expect("verification failed");
+    backend.verify(&pis, &proof).expect("verification failed");
     println!("verification successful");
 }
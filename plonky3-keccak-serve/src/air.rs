@@ -0,0 +1,290 @@
+use std::borrow::Borrow;
+
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir, SubAirBuilder};
+use p3_field::{AbstractField, PrimeField64};
+use p3_keccak_air::{
+    generate_trace_rows, KeccakAir, KeccakCols, NUM_KECCAK_COLS, NUM_ROUNDS, U64_LIMBS,
+};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+
+use crate::witness::{padded_inputs, permutations, KeccakPublicValues, MAX_PERMUTATIONS, RATE};
+
+/// Values of the low digit of a permutation's index.
+const INDEX_LOW: usize = 128;
+/// Values of the high digit, enough for every permutation of the largest trace, including the
+/// dummy one it ends with.
+const INDEX_HIGH: usize = (MAX_PERMUTATIONS + 1).div_ceil(INDEX_LOW);
+
+/// State lanes blocks are absorbed into; the others are the capacity.
+const RATE_LANES: usize = RATE / 8;
+/// Lanes the Keccak-256 digest is squeezed from.
+const DIGEST_LANES: usize = 4;
+
+/// Columns [`KeccakHashAir`] adds after those of `KeccakAir`, the same on every row of a
+/// permutation.
+#[repr(C)]
+pub struct HashCols<T> {
+    /// The permutation's index, `index_high * INDEX_LOW + index_low`, as two one-hot digits.
+    pub index_low: [T; INDEX_LOW],
+    pub index_high: [T; INDEX_HIGH],
+    /// Set on the last round of a permutation whose `index_low` is the highest, so that the next
+    /// permutation carries into `index_high`.
+    pub carry: T,
+    /// Set if the permutation is one of the listed ones rather than a dummy.
+    pub is_real: T,
+    /// Set if the permutation carries on the hash of the one before.
+    pub continues: T,
+    /// Set if the permutation is the last of its hash.
+    pub ends: T,
+    /// Number of hashes the permutations before this one end, which the last row holds for the
+    /// whole trace.
+    pub hashes: T,
+}
+
+pub const NUM_HASH_COLS: usize = std::mem::size_of::<HashCols<u8>>();
+
+impl<T> Borrow<HashCols<T>> for [T] {
+    fn borrow(&self) -> &HashCols<T> {
+        debug_assert_eq!(self.len(), NUM_HASH_COLS);
+        let (prefix, shorts, suffix) = unsafe { self.align_to::<HashCols<T>>() };
+        debug_assert!(prefix.is_empty(), "Alignment should match");
+        debug_assert!(suffix.is_empty(), "Alignment should match");
+        debug_assert_eq!(shorts.len(), 1);
+        &shorts[0]
+    }
+}
+
+/// `KeccakAir`, plus constraints tying its permutations to a [`KeccakPublicValues`]: the input of
+/// every real permutation is the listed one, a permutation that carries on a hash starts from the
+/// capacity the one before left, the last permutation of each hash outputs its digest, and as
+/// many hashes end as the public values count.
+///
+/// The public values are indexed by permutation, so the AIR is built for the hashes of one
+/// statement and each of these constraints sums over its permutations.
+pub struct KeccakHashAir {
+    hashes: usize,
+    /// Whether each real permutation carries on the hash of the one before.
+    continues: Vec<bool>,
+    /// The hash each real permutation ends, if any.
+    ends: Vec<Option<usize>>,
+}
+
+impl KeccakHashAir {
+    /// The AIR of the hashes `pis` lists, which must [`check`](KeccakPublicValues::check).
+    pub fn new(pis: &KeccakPublicValues) -> Self {
+        let (mut continues, mut ends) = (vec![], vec![]);
+        for (hash, &len) in pis.message_lengths.iter().enumerate() {
+            let permutations = permutations(len) as usize;
+            continues.extend((0..permutations).map(|i| i > 0));
+            ends.extend((0..permutations).map(|i| (i + 1 == permutations).then_some(hash)));
+        }
+        KeccakHashAir {
+            hashes: pis.message_lengths.len(),
+            continues,
+            ends,
+        }
+    }
+
+    /// The `KeccakAir` trace of `inputs` and their dummy permutations, with the columns of
+    /// [`HashCols`] after each row.
+    pub fn generate_trace_rows<F: PrimeField64>(&self, inputs: &[[u64; 25]]) -> RowMajorMatrix<F> {
+        let keccak = generate_trace_rows::<F>(padded_inputs(inputs));
+        let width = NUM_KECCAK_COLS + NUM_HASH_COLS;
+        let mut values = Vec::with_capacity(keccak.height() * width);
+        let mut hashes = 0;
+        for (row, keccak_row) in keccak.values.chunks_exact(NUM_KECCAK_COLS).enumerate() {
+            let (permutation, round) = (row / NUM_ROUNDS, row % NUM_ROUNDS);
+            let (low, high) = (
+                permutation % INDEX_LOW,
+                permutation / INDEX_LOW % INDEX_HIGH,
+            );
+            values.extend_from_slice(keccak_row);
+            values.extend((0..INDEX_LOW).map(|i| F::from_bool(i == low)));
+            values.extend((0..INDEX_HIGH).map(|i| F::from_bool(i == high)));
+            values.push(F::from_bool(
+                round == NUM_ROUNDS - 1 && low == INDEX_LOW - 1,
+            ));
+            let ends = self.ends.get(permutation).is_some_and(Option::is_some);
+            values.push(F::from_bool(permutation < self.continues.len()));
+            values.push(F::from_bool(
+                self.continues.get(permutation).copied().unwrap_or(false),
+            ));
+            values.push(F::from_bool(ends));
+            values.push(F::from_canonical_usize(hashes));
+            if ends && round == NUM_ROUNDS - 1 {
+                hashes += 1;
+            }
+        }
+        RowMajorMatrix::new(values, width)
+    }
+}
+
+impl<F> BaseAir<F> for KeccakHashAir {
+    fn width(&self) -> usize {
+        NUM_KECCAK_COLS + NUM_HASH_COLS
+    }
+}
+
+impl<AB: AirBuilderWithPublicValues> Air<AB> for KeccakHashAir {
+    fn eval(&self, builder: &mut AB) {
+        KeccakAir {}.eval(&mut SubAirBuilder::<AB, KeccakAir, AB::F>::new(
+            builder,
+            0..NUM_KECCAK_COLS,
+        ));
+
+        let main = builder.main();
+        let pis = builder.public_values().to_vec();
+        let pi = |i: usize| -> AB::Expr { pis[i].into() };
+        let (local, next) = (main.row_slice(0), main.row_slice(1));
+        let (local_keccak, local) = local.split_at(NUM_KECCAK_COLS);
+        let (next_keccak, next) = next.split_at(NUM_KECCAK_COLS);
+        let local_keccak: &KeccakCols<AB::Var> = local_keccak.borrow();
+        let next_keccak: &KeccakCols<AB::Var> = next_keccak.borrow();
+        let local: &HashCols<AB::Var> = local.borrow();
+        let next: &HashCols<AB::Var> = next.borrow();
+        let last_round: AB::Expr = local_keccak.step_flags[NUM_ROUNDS - 1].into();
+
+        // the index starts at 0 and goes up by one from each permutation to the next
+        let mut when_first_row = builder.when_first_row();
+        for (i, &digit) in local.index_low.iter().enumerate() {
+            when_first_row.assert_eq(digit, AB::Expr::from_bool(i == 0));
+        }
+        for (i, &digit) in local.index_high.iter().enumerate() {
+            when_first_row.assert_eq(digit, AB::Expr::from_bool(i == 0));
+        }
+        builder.assert_eq(
+            local.carry,
+            last_round.clone() * local.index_low[INDEX_LOW - 1],
+        );
+        let mut when_transition = builder.when_transition();
+        for i in 0..INDEX_LOW {
+            let below = local.index_low[(i + INDEX_LOW - 1) % INDEX_LOW];
+            when_transition.assert_eq(
+                next.index_low[i],
+                local.index_low[i] + last_round.clone() * (below - local.index_low[i]),
+            );
+        }
+        for i in 0..INDEX_HIGH {
+            let below = local.index_high[(i + INDEX_HIGH - 1) % INDEX_HIGH];
+            when_transition.assert_eq(
+                next.index_high[i],
+                local.index_high[i] + local.carry * (below - local.index_high[i]),
+            );
+        }
+
+        // one selector per real permutation, set on its rows only
+        let permutations = self.continues.len();
+        let selectors = (0..permutations)
+            .map(|k| local.index_high[k / INDEX_LOW] * local.index_low[k % INDEX_LOW])
+            .collect::<Vec<AB::Expr>>();
+        let select = |keep: &dyn Fn(usize) -> bool| -> AB::Expr {
+            (0..permutations)
+                .filter(|&k| keep(k))
+                .map(|k| selectors[k].clone())
+                .sum()
+        };
+        builder.assert_eq(local.is_real, select(&|_| true));
+        builder.assert_eq(local.continues, select(&|k| self.continues[k]));
+        builder.assert_eq(local.ends, select(&|k| self.ends[k].is_some()));
+
+        // the hashes ending in the trace are the ones counted; its last row is never a last round,
+        // as 24 rounds do not divide a power-of-two height
+        builder.when_first_row().assert_zero(local.hashes);
+        builder
+            .when_transition()
+            .assert_eq(next.hashes, local.hashes + last_round.clone() * local.ends);
+        let num_hashes = (0..U64_LIMBS)
+            .map(|i| pi(i) * AB::Expr::from_wrapped_u64(1 << (16 * i)))
+            .sum::<AB::Expr>();
+        builder.when_last_row().assert_eq(local.hashes, num_hashes);
+
+        // laid out as `KeccakPublicValues::to_field_elements` does
+        let inputs_start = U64_LIMBS + U64_LIMBS * self.hashes;
+        let digests_start = inputs_start + 25 * U64_LIMBS * permutations;
+        for lane in 0..25 {
+            let (y, x) = (lane / 5, lane % 5);
+            for limb in 0..U64_LIMBS {
+                // the input of a real permutation is the listed one
+                let offset = inputs_start + lane * U64_LIMBS + limb;
+                let input = selectors
+                    .iter()
+                    .enumerate()
+                    .map(|(k, selector)| selector.clone() * pi(offset + k * 25 * U64_LIMBS))
+                    .sum::<AB::Expr>();
+                builder.assert_eq(local.is_real * local_keccak.preimage[y][x][limb], input);
+
+                // a permutation carrying on a hash starts from the capacity the one before left
+                if lane >= RATE_LANES {
+                    builder
+                        .when_transition()
+                        .when(last_round.clone() * next.continues)
+                        .assert_eq(
+                            next_keccak.preimage[y][x][limb],
+                            output(local_keccak, y, x, limb),
+                        );
+                }
+
+                // the last permutation of a hash outputs its digest
+                if lane < DIGEST_LANES {
+                    let offset = digests_start + lane * U64_LIMBS + limb;
+                    let digest = selectors
+                        .iter()
+                        .zip(&self.ends)
+                        .filter_map(|(selector, hash)| {
+                            hash.map(|hash| {
+                                selector.clone() * pi(offset + hash * DIGEST_LANES * U64_LIMBS)
+                            })
+                        })
+                        .sum::<AB::Expr>();
+                    builder
+                        .when(last_round.clone())
+                        .assert_eq(local.ends * output(local_keccak, y, x, limb), digest);
+                }
+            }
+        }
+    }
+}
+
+/// A limb of lane `(x, y)` of the state a permutation outputs, as of its last round.
+fn output<T: Copy>(cols: &KeccakCols<T>, y: usize, x: usize, limb: usize) -> T {
+    if (y, x) == (0, 0) {
+        cols.a_prime_prime_prime_0_0_limbs[limb]
+    } else {
+        cols.a_prime_prime[y][x][limb]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p3_baby_bear::BabyBear;
+    use plonky3_config::babybear;
+    use plonky3_config::dft::Dft;
+    use plonky3_config::perm::PermSource;
+
+    use super::*;
+    use crate::witness::padded_state;
+    use crate::DEFAULT_FRI_PARAMS;
+
+    /// Under debug assertions `prove` checks the trace against every constraint first, so a count
+    /// the transcript would merely carry does not get that far.
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "constraints had nonzero value")]
+    fn hash_count_is_constrained() {
+        let perm = PermSource::default().constants().unwrap().to_perm();
+        let stark = babybear::poseidon2_stark::<4>(&perm, Dft::default(), &DEFAULT_FRI_PARAMS);
+        let inputs = vec![padded_state(b"abc"), padded_state(b"def")];
+        let mut pis = KeccakPublicValues::new(&inputs);
+        let air = KeccakHashAir::new(&pis);
+        let trace = air.generate_trace_rows::<BabyBear>(&inputs);
+        pis.num_hashes = 1;
+        p3_uni_stark::prove(
+            &stark.config,
+            &air,
+            &mut stark.challenger(),
+            trace,
+            &pis.to_field_elements(),
+        );
+    }
+}
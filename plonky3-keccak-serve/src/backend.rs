@@ -0,0 +1,330 @@
+use std::collections::HashMap;
+
+use p3_field::PrimeField64;
+use p3_keccak_air::NUM_KECCAK_COLS;
+use p3_uni_stark::{Proof, StarkGenericConfig, Val};
+use plonky3_config::perm::PermSource;
+use plonky3_config::stark::{verify_error, Provers, Stark, StarkVisitor};
+use prover_serve::backend::{ProofBackend, VerifyError, VerifyErrorKind};
+use prover_serve::fri::FriParams;
+use prover_serve::hash::HashId;
+use prover_serve::metrics::{self, Phase};
+use prover_serve::preset::Preset;
+use prover_serve::proof::{ProofBody, ProofConfig, TaggedProof};
+use prover_serve::vk::VerifyingKey;
+
+use crate::air::{KeccakHashAir, NUM_HASH_COLS};
+use crate::witness::{num_permutations, KeccakPublicValues, MAX_PERMUTATIONS, RATE};
+use crate::MyWitness;
+
+const AIR: &str = "KeccakHashAir";
+
+/// Largest `/prove/keccak256` body: as many bytes as [`MAX_PERMUTATIONS`] can absorb.
+const MAX_BODY_BYTES: usize = MAX_PERMUTATIONS * RATE;
+
+/// Commitments use Keccak-256 unless `--hash` says otherwise.
+pub const DEFAULT_HASH: HashId = HashId::Keccak;
+
+/// Proves and verifies [`KeccakHashAir`] under one [`Stark`], whatever its configuration type.
+trait KeccakStark: Send + Sync {
+    fn prove(&self, witness: MyWitness) -> Result<ProofBody, String>;
+
+    fn verify(&self, pis: &KeccakPublicValues, proof: &[u8]) -> Result<(), VerifyError>;
+}
+
+impl<SC> KeccakStark for Stark<SC>
+where
+    SC: StarkGenericConfig + Send + Sync + 'static,
+    SC::Challenger: Clone + Send + Sync,
+    Val<SC>: PrimeField64,
+    Proof<SC>: Send + Sync,
+{
+    fn prove(&self, witness: MyWitness) -> Result<ProofBody, String> {
+        let (air, trace, pis) = metrics::time(AIR, Phase::TraceGeneration, || {
+            witness.check()?;
+            let pis = KeccakPublicValues::new(&witness.inputs);
+            let air = KeccakHashAir::new(&pis);
+            let trace = air.generate_trace_rows::<Val<SC>>(&witness.inputs);
+            Ok::<_, String>((air, trace, pis.to_field_elements()))
+        })?;
+        let mut challenger = self.challenger();
+        let proof = metrics::time(AIR, Phase::Proving, || {
+            p3_uni_stark::prove(&self.config, &air, &mut challenger, trace, &pis)
+        });
+        Ok(ProofBody::new(proof))
+    }
+
+    fn verify(&self, pis: &KeccakPublicValues, proof: &[u8]) -> Result<(), VerifyError> {
+        let proof = postcard::from_bytes::<Proof<SC>>(proof)
+            .map_err(|e| VerifyError::new(VerifyErrorKind::ProofDecode, e.to_string()))?;
+        let mut challenger = self.challenger();
+        p3_uni_stark::verify(
+            &self.config,
+            &KeccakHashAir::new(pis),
+            &mut challenger,
+            &proof,
+            &pis.to_field_elements(),
+        )
+        .map_err(verify_error)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Boxed;
+
+impl StarkVisitor for Boxed {
+    type Output = Box<dyn KeccakStark>;
+
+    fn visit<SC>(self, stark: Stark<SC>) -> Box<dyn KeccakStark>
+    where
+        SC: StarkGenericConfig + Send + Sync + 'static,
+        SC::Challenger: Clone + Send + Sync,
+        Val<SC>: PrimeField64,
+        Proof<SC>: Send + Sync,
+    {
+        Box::new(stark)
+    }
+}
+
+pub struct KeccakBackend {
+    /// Built once and shared by every proof and verification.
+    provers: Provers<Box<dyn KeccakStark>>,
+    config: ProofConfig,
+    vk: VerifyingKey,
+}
+
+impl KeccakBackend {
+    /// A backend proving under `config`, with Poseidon2 constants from `perm_source` if it
+    /// commits with Poseidon2, and with `fri` unless a request picks one of `presets`.
+    pub fn new(
+        config: ProofConfig,
+        perm_source: &PermSource,
+        fri: FriParams,
+        presets: &[Preset],
+    ) -> Result<Self, String> {
+        let (provers, hash) = Provers::build(&config, perm_source, &fri, presets, Boxed)?;
+        let vk = VerifyingKey {
+            air: AIR.to_string(),
+            field: config.field.to_string(),
+            extension_degree: config.extension_degree as u64,
+            trace_width: (NUM_KECCAK_COLS + NUM_HASH_COLS) as u64,
+            log_blowup: fri.log_blowup as u64,
+            num_queries: fri.num_queries as u64,
+            proof_of_work_bits: fri.proof_of_work_bits as u64,
+            hash: hash.name,
+            hash_constants_digest: hash.constants_digest,
+        };
+        Ok(KeccakBackend {
+            provers,
+            config,
+            vk,
+        })
+    }
+
+    fn prove_with(
+        &self,
+        witness: MyWitness,
+        preset: Option<Preset>,
+    ) -> Result<TaggedProof, String> {
+        Ok(TaggedProof {
+            config: self.config,
+            preset,
+            proof: self.provers.get(preset)?.prove(witness)?,
+        })
+    }
+}
+
+impl ProofBackend for KeccakBackend {
+    type Witness = MyWitness;
+    type PublicInputs = KeccakPublicValues;
+    type Proof = TaggedProof;
+
+    const WITNESS_FORMATS: &'static [&'static str] = &["keccak256"];
+
+    fn prove(&self, witness: MyWitness) -> Result<TaggedProof, String> {
+        self.prove_with(witness, None)
+    }
+
+    fn prove_preset(&self, witness: MyWitness, preset: Preset) -> Result<TaggedProof, String> {
+        self.prove_with(witness, Some(preset))
+    }
+
+    fn presets(&self) -> Vec<Preset> {
+        self.provers.presets()
+    }
+
+    fn verify(&self, pis: &KeccakPublicValues, proof: &TaggedProof) -> Result<(), VerifyError> {
+        pis.check()
+            .map_err(|e| VerifyError::new(VerifyErrorKind::InvalidPublicInputs, e))?;
+        let bytes = proof.check(&self.config)?;
+        let stark = self
+            .provers
+            .get(proof.preset)
+            .map_err(|e| VerifyError::new(VerifyErrorKind::PresetNotAllowed, e))?;
+        stark.verify(pis, &bytes)
+    }
+
+    /// Checks the proofs on rayon's threads with the `parallel` feature.
+    #[cfg(feature = "parallel")]
+    fn verify_batch(
+        &self,
+        batch: &[(KeccakPublicValues, TaggedProof)],
+    ) -> Vec<Result<(), VerifyError>> {
+        use rayon::prelude::*;
+
+        batch
+            .par_iter()
+            .map(|(pis, proof)| self.verify(pis, proof))
+            .collect()
+    }
+
+    fn verifying_key(&self) -> VerifyingKey {
+        self.vk.clone()
+    }
+
+    /// The body is hashed as one message, or as consecutive messages of `chunk_size` bytes each
+    /// when given, like the arena's 64-byte blocks, in at most [`MAX_PERMUTATIONS`] permutations.
+    fn generate(
+        &self,
+        _format: &str,
+        query: &HashMap<String, String>,
+        body: &[u8],
+    ) -> Result<(MyWitness, KeccakPublicValues), String> {
+        if body.len() > MAX_BODY_BYTES {
+            return Err(format!(
+                "body of {} bytes is larger than the {} bytes a proof can hash",
+                body.len(),
+                MAX_BODY_BYTES
+            ));
+        }
+        let messages = match query.get("chunk_size") {
+            None => vec![body],
+            Some(chunk_size) => {
+                let chunk_size = chunk_size
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&chunk_size| chunk_size > 0)
+                    .ok_or_else(|| format!("invalid chunk_size {:?}", chunk_size))?;
+                if body.is_empty() || body.len() % chunk_size != 0 {
+                    return Err(format!(
+                        "body of {} bytes is not a positive multiple of chunk_size {}",
+                        body.len(),
+                        chunk_size
+                    ));
+                }
+                body.chunks_exact(chunk_size).collect()
+            }
+        };
+        let permutations = messages
+            .iter()
+            .map(|message| num_permutations(message.len()))
+            .sum::<usize>();
+        if permutations > MAX_PERMUTATIONS {
+            return Err(format!(
+                "hashing takes {} permutations, more than the {} a proof can hold",
+                permutations, MAX_PERMUTATIONS
+            ));
+        }
+        let witness = MyWitness::from_messages(messages);
+        let pis = KeccakPublicValues::new(&witness.inputs);
+        Ok((witness, pis))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prover_serve::field::FieldId;
+
+    use super::*;
+    use crate::witness::padded_state;
+    use crate::DEFAULT_FRI_PARAMS;
+
+    fn backend() -> KeccakBackend {
+        let config = ProofConfig::new(FieldId::BabyBear, DEFAULT_HASH);
+        KeccakBackend::new(config, &PermSource::default(), DEFAULT_FRI_PARAMS, &[]).unwrap()
+    }
+
+    #[test]
+    fn proof_does_not_verify_other_hashes() {
+        let backend = backend();
+        let witness = MyWitness {
+            inputs: vec![padded_state(b"abc"), padded_state(b"def")],
+        };
+        let pis = KeccakPublicValues::new(&witness.inputs);
+        let proof = backend.prove(witness).unwrap();
+        backend.verify(&pis, &proof).unwrap();
+
+        let other = KeccakPublicValues::new(&[padded_state(b"abd"), padded_state(b"def")]);
+        other.check().unwrap();
+        assert!(backend.verify(&other, &proof).is_err());
+    }
+
+    #[test]
+    fn proof_does_not_verify_another_digest() {
+        let backend = backend();
+        let witness = MyWitness {
+            inputs: vec![padded_state(b"abc"), padded_state(b"def")],
+        };
+        let pis = KeccakPublicValues::new(&witness.inputs);
+        let proof = backend.prove(witness).unwrap();
+
+        // not recomputed on verify, so only the proof rules it out
+        let mut wrong = pis;
+        wrong.digests[1] = wrong.digests[0];
+        wrong.check().unwrap();
+        let error = backend.verify(&wrong, &proof).unwrap_err();
+        assert_ne!(error.kind, VerifyErrorKind::InvalidPublicInputs);
+    }
+
+    #[test]
+    fn proof_does_not_verify_another_hash_count() {
+        let backend = backend();
+        let witness = MyWitness {
+            inputs: vec![padded_state(b"abc"), padded_state(b"def")],
+        };
+        let pis = KeccakPublicValues::new(&witness.inputs);
+        let proof = backend.prove(witness).unwrap();
+
+        let mut more = pis.clone();
+        more.num_hashes += 1;
+        let error = backend.verify(&more, &proof).unwrap_err();
+        assert_eq!(error.kind, VerifyErrorKind::InvalidPublicInputs);
+
+        // consistent on their own, but not what the proof was made with
+        let fewer = KeccakPublicValues::new(&pis.inputs[..1]);
+        assert_eq!(fewer.num_hashes, 1);
+        assert!(backend.verify(&fewer, &proof).is_err());
+    }
+
+    #[test]
+    fn raw_states_are_hashed_on_their_own() {
+        let backend = backend();
+        let witness = MyWitness {
+            inputs: vec![[1; 25], [2; 25], [3; 25]],
+        };
+        let pis = KeccakPublicValues::new(&witness.inputs);
+        assert_eq!(pis.message_lengths, vec![None; 3]);
+        let proof = backend.prove(witness).unwrap();
+        backend.verify(&pis, &proof).unwrap();
+
+        let mut wrong = pis;
+        wrong.digests.swap(0, 1);
+        assert!(backend.verify(&wrong, &proof).is_err());
+    }
+
+    #[test]
+    fn oversize_input_is_rejected() {
+        let backend = backend();
+        let body = vec![0u8; MAX_BODY_BYTES + 1];
+        assert!(backend
+            .generate("keccak256", &HashMap::new(), &body)
+            .is_err());
+
+        let chunked = HashMap::from([("chunk_size".to_string(), "1".to_string())]);
+        let body = vec![0u8; MAX_PERMUTATIONS + 1];
+        assert!(backend.generate("keccak256", &chunked, &body).is_err());
+        assert!(backend
+            .generate("keccak256", &chunked, &body[..MAX_PERMUTATIONS])
+            .is_ok());
+    }
+}
@@ -0,0 +1,423 @@
+use p3_field::PrimeField64;
+use serde::{Deserialize, Serialize};
+
+use crate::MyWitness;
+
+/// Size of the input blocks the proof arena hashes one by one.
+pub const CHUNK_SIZE: usize = 64;
+/// Keccak-256 rate in bytes.
+pub const RATE: usize = 136;
+
+/// State lanes the blocks are absorbed into; the others are the capacity.
+const RATE_LANES: usize = RATE / 8;
+
+const BITS_PER_LIMB: usize = 16;
+/// Trace rows per Keccak-f call in `KeccakAir`.
+const ROWS_PER_PERMUTATION: usize = 24;
+/// Most permutations a proof can hold, filling a trace of at most 2^18 rows.
+pub const MAX_PERMUTATIONS: usize = (1 << 18) / ROWS_PER_PERMUTATION;
+
+/// Absorbs a message shorter than one rate block into the all-zero state, i.e. returns the input
+/// of the single Keccak-f call whose output holds the Keccak-256 digest of `message`.
+pub fn padded_state(message: &[u8]) -> [u64; 25] {
+    assert!(message.len() < RATE, "message does not fit in one block");
+    let mut block = [0u8; RATE];
+    block[..message.len()].copy_from_slice(message);
+    block[message.len()] ^= 0x01;
+    block[RATE - 1] ^= 0x80;
+    let mut state = [0u64; 25];
+    for (lane, bytes) in state.iter_mut().zip(block.chunks_exact(8)) {
+        *lane = u64::from_le_bytes(bytes.try_into().unwrap());
+    }
+    state
+}
+
+/// Absorbs `message` of any length into the all-zero state, returning the input of every Keccak-f
+/// call. The output of the last one holds the Keccak-256 digest of `message`.
+pub fn absorb(message: &[u8]) -> Vec<[u64; 25]> {
+    let mut padded = message.to_vec();
+    padded.resize((message.len() / RATE + 1) * RATE, 0);
+    padded[message.len()] ^= 0x01;
+    *padded.last_mut().unwrap() ^= 0x80;
+    let mut state = [0u64; 25];
+    padded
+        .chunks_exact(RATE)
+        .map(|block| {
+            for (lane, bytes) in state.iter_mut().zip(block.chunks_exact(8)) {
+                *lane ^= u64::from_le_bytes(bytes.try_into().unwrap());
+            }
+            let input = state;
+            tiny_keccak::keccakf(&mut state);
+            input
+        })
+        .collect()
+}
+
+/// Number of permutations [`absorb`] takes for a message of `len` bytes.
+pub fn num_permutations(len: usize) -> usize {
+    len / RATE + 1
+}
+
+/// Splits `inputs` back into the messages [`absorb`] made them from. A message starts at an input
+/// whose capacity lanes are all zero and goes on while the next input carries the capacity the
+/// previous permutation left, so inputs that are not chained that way, or whose last block is not
+/// padded, are rejected.
+pub fn messages(inputs: &[[u64; 25]]) -> Result<Vec<Vec<u8>>, String> {
+    let mut messages = vec![];
+    let mut next = 0;
+    while next < inputs.len() {
+        let start = next;
+        let mut state = [0u64; 25];
+        let mut padded = vec![];
+        loop {
+            let input = &inputs[next];
+            if input[RATE_LANES..] != state[RATE_LANES..] {
+                return Err(format!(
+                    "permutation {} does not start or continue a message",
+                    next
+                ));
+            }
+            for (lane, previous) in input[..RATE_LANES].iter().zip(&state[..RATE_LANES]) {
+                padded.extend_from_slice(&(lane ^ previous).to_le_bytes());
+            }
+            state = *input;
+            tiny_keccak::keccakf(&mut state);
+            next += 1;
+            if next == inputs.len() || inputs[next][RATE_LANES..] != state[RATE_LANES..] {
+                break;
+            }
+        }
+        let message = unpad(&padded).ok_or_else(|| {
+            format!(
+                "permutations {}..{} do not absorb a padded message",
+                start, next
+            )
+        })?;
+        messages.push(message);
+    }
+    Ok(messages)
+}
+
+/// The bytes of the rate lanes of `state`.
+fn rate_bytes(state: &[u64; 25]) -> Vec<u8> {
+    state[..RATE_LANES]
+        .iter()
+        .flat_map(|lane| lane.to_le_bytes())
+        .collect()
+}
+
+/// Strips the pad10*1 padding [`absorb`] adds, if `padded` ends in it.
+fn unpad(padded: &[u8]) -> Option<Vec<u8>> {
+    let (&last, rest) = padded.split_last()?;
+    let len = match last {
+        0x81 => rest.len(),
+        0x80 => {
+            let end = rest.iter().rposition(|&byte| byte != 0)?;
+            if rest[end] != 0x01 {
+                return None;
+            }
+            end
+        }
+        _ => return None,
+    };
+    if padded.len() != num_permutations(len) * RATE {
+        return None;
+    }
+    Some(padded[..len].to_vec())
+}
+
+/// Keccak-256 digest squeezed from the permutation applied to `input`.
+pub fn digest(input: &[u64; 25]) -> [u8; 32] {
+    let mut state = *input;
+    tiny_keccak::keccakf(&mut state);
+    let mut output = [0u8; 32];
+    for (bytes, lane) in output.chunks_exact_mut(8).zip(state) {
+        bytes.copy_from_slice(&lane.to_le_bytes());
+    }
+    output
+}
+
+impl MyWitness {
+    /// One permutation per [`CHUNK_SIZE`]-byte block of `bytes`, which must be a whole number of
+    /// blocks, as the arena's baseline hasher requires too.
+    pub fn from_chunks(bytes: &[u8]) -> Self {
+        assert!(
+            bytes.len().is_multiple_of(CHUNK_SIZE),
+            "input is not a whole number of chunks"
+        );
+        MyWitness {
+            inputs: bytes.chunks_exact(CHUNK_SIZE).map(padded_state).collect(),
+        }
+    }
+
+    /// The permutations hashing each of `messages`, one after the other.
+    pub fn from_messages<'a>(messages: impl IntoIterator<Item = &'a [u8]>) -> Self {
+        MyWitness {
+            inputs: messages.into_iter().flat_map(absorb).collect(),
+        }
+    }
+
+    pub fn check(&self) -> Result<(), String> {
+        if self.inputs.len() > MAX_PERMUTATIONS {
+            return Err(format!(
+                "{} permutations, more than the {} a proof can hold",
+                self.inputs.len(),
+                MAX_PERMUTATIONS
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// `inputs` followed by all-zero dummy permutations filling the power-of-two trace that
+/// `generate_trace_rows` builds for them. Rows left over after the last whole permutation are
+/// filled by `generate_trace_rows` itself.
+pub fn padded_inputs(inputs: &[[u64; 25]]) -> Vec<[u64; 25]> {
+    let rows = (inputs.len().max(1) * ROWS_PER_PERMUTATION).next_power_of_two();
+    let mut padded = inputs.to_vec();
+    padded.resize(rows / ROWS_PER_PERMUTATION, [0; 25]);
+    padded
+}
+
+/// What a keccak proof is checked against: the number of hashed messages, their lengths, the
+/// permutation inputs absorbing them one after the other, and the Keccak-256 digest of each
+/// message. Dummy permutations padding the trace are not listed.
+///
+/// Inputs that do not absorb whole messages, such as the raw states clients sent before messages
+/// were listed, are each hashed on their own: their length is `None` and their digest is squeezed
+/// from the output of their permutation.
+///
+/// [`KeccakHashAir`](crate::air::KeccakHashAir) constrains the proof's permutations to these
+/// inputs, chained through their capacity within each message, and to output these digests.
+/// [`check`](Self::check) covers what can be seen without hashing: that every message starts from
+/// the all-zero capacity, takes as many permutations as its length says, and, if it fits in one
+/// block, is padded to that length. The padding of longer messages is absorbed into a state the
+/// verifier does not see, so their length only sets how many permutations they take.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeccakPublicValues {
+    pub num_hashes: u64,
+    pub message_lengths: Vec<Option<u64>>,
+    pub inputs: Vec<[u64; 25]>,
+    pub digests: Vec<[u8; 32]>,
+}
+
+impl KeccakPublicValues {
+    /// The messages `inputs` absorb, see [`messages`], or each of `inputs` as a raw state if they
+    /// do not absorb whole messages.
+    pub fn new(inputs: &[[u64; 25]]) -> Self {
+        let message_lengths = match messages(inputs) {
+            Ok(messages) => messages
+                .iter()
+                .map(|message| Some(message.len() as u64))
+                .collect::<Vec<_>>(),
+            Err(_) => vec![None; inputs.len()],
+        };
+        let mut end = 0;
+        let digests = message_lengths
+            .iter()
+            .map(|&len| {
+                end += permutations(len) as usize;
+                digest(&inputs[end - 1])
+            })
+            .collect();
+        KeccakPublicValues {
+            num_hashes: message_lengths.len() as u64,
+            message_lengths,
+            inputs: inputs.to_vec(),
+            digests,
+        }
+    }
+
+    pub fn check(&self) -> Result<(), String> {
+        if self.message_lengths.len() as u64 != self.num_hashes
+            || self.digests.len() as u64 != self.num_hashes
+        {
+            return Err(format!(
+                "{} hashes but {} message lengths and {} digests",
+                self.num_hashes,
+                self.message_lengths.len(),
+                self.digests.len()
+            ));
+        }
+        let permutations = self
+            .message_lengths
+            .iter()
+            .map(|&len| permutations(len))
+            .fold(0u64, u64::saturating_add);
+        if permutations != self.inputs.len() as u64 {
+            return Err(format!(
+                "the messages take {} permutations, not {}",
+                permutations,
+                self.inputs.len()
+            ));
+        }
+        if self.inputs.len() > MAX_PERMUTATIONS {
+            return Err(format!(
+                "{} permutations, more than the {} a proof can hold",
+                self.inputs.len(),
+                MAX_PERMUTATIONS
+            ));
+        }
+        let mut start = 0;
+        for (i, &len) in self.message_lengths.iter().enumerate() {
+            let Some(len) = len.map(|len| len as usize) else {
+                start += 1;
+                continue;
+            };
+            let input = &self.inputs[start];
+            if input[RATE_LANES..].iter().any(|&lane| lane != 0) {
+                return Err(format!(
+                    "message {} does not start from the all-zero state",
+                    i
+                ));
+            }
+            if len < RATE && unpad(&rate_bytes(input)).map(|message| message.len()) != Some(len) {
+                return Err(format!("message {} is not padded to {} bytes", i, len));
+            }
+            start += num_permutations(len);
+        }
+        Ok(())
+    }
+
+    /// 16-bit limbs of the hash count, every message length plus one (zero for a raw state), every
+    /// input lane and every digest,
+    /// matching the limb decomposition `KeccakAir` uses for its lanes, which is where
+    /// [`KeccakHashAir`](crate::air::KeccakHashAir) reads the inputs and digests from.
+    pub fn to_field_elements<F: PrimeField64>(&self) -> Vec<F> {
+        let count_limbs = limbs(self.num_hashes);
+        let length_limbs = self
+            .message_lengths
+            .iter()
+            .flat_map(|len| limbs(len.map_or(0, |len| len.saturating_add(1))));
+        let input_limbs = self.inputs.iter().flatten().flat_map(|lane| limbs(*lane));
+        let digest_limbs = self
+            .digests
+            .iter()
+            .flat_map(|digest| digest.chunks_exact(8))
+            .flat_map(|bytes| limbs(u64::from_le_bytes(bytes.try_into().unwrap())));
+        count_limbs
+            .chain(length_limbs)
+            .chain(input_limbs)
+            .chain(digest_limbs)
+            .collect()
+    }
+}
+
+/// Permutations a hash of a message of `len` bytes, or of a raw state, takes.
+pub fn permutations(len: Option<u64>) -> u64 {
+    len.map_or(1, |len| len / RATE as u64 + 1)
+}
+
+fn limbs<F: PrimeField64>(lane: u64) -> impl Iterator<Item = F> {
+    (0..64 / BITS_PER_LIMB).map(move |i| {
+        F::from_canonical_u64((lane >> (i * BITS_PER_LIMB)) & ((1 << BITS_PER_LIMB) - 1))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tiny_keccak::{Hasher, Keccak};
+
+    use super::*;
+
+    const LENGTHS: [usize; 6] = [0, 64, 135, 136, 137, 272];
+
+    fn message(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 7 + 3) as u8).collect()
+    }
+
+    fn keccak256(message: &[u8]) -> [u8; 32] {
+        let mut hasher = Keccak::v256();
+        let mut output = [0u8; 32];
+        hasher.update(message);
+        hasher.finalize(&mut output);
+        output
+    }
+
+    #[test]
+    fn absorbed_digest_matches_tiny_keccak() {
+        for len in LENGTHS {
+            let message = message(len);
+            let inputs = absorb(&message);
+            assert_eq!(inputs.len(), len / RATE + 1, "{} bytes", len);
+            assert_eq!(
+                digest(inputs.last().unwrap()),
+                keccak256(&message),
+                "{} bytes",
+                len
+            );
+        }
+    }
+
+    #[test]
+    fn public_values_list_each_message() {
+        let messages = LENGTHS.map(message);
+        let witness = MyWitness::from_messages(messages.iter().map(Vec::as_slice));
+        assert_eq!(super::messages(&witness.inputs).unwrap(), messages);
+
+        let pis = KeccakPublicValues::new(&witness.inputs);
+        assert_eq!(pis.num_hashes, LENGTHS.len() as u64);
+        assert_eq!(pis.message_lengths, LENGTHS.map(|len| Some(len as u64)));
+        assert_eq!(pis.digests, messages.map(|message| keccak256(&message)));
+        pis.check().unwrap();
+    }
+
+    #[test]
+    fn unchained_inputs_are_raw_states() {
+        let mut inputs = absorb(&message(272));
+        inputs.swap(1, 2);
+        assert!(super::messages(&inputs).is_err());
+        let pis = KeccakPublicValues::new(&inputs);
+        assert_eq!(pis.num_hashes, 3);
+        assert_eq!(pis.message_lengths, vec![None; 3]);
+        assert_eq!(pis.digests, inputs.iter().map(digest).collect::<Vec<_>>());
+        pis.check().unwrap();
+
+        // a block absorbed into the all-zero state that is not padded
+        assert!(super::messages(&[[0; 25]]).is_err());
+        assert_eq!(KeccakPublicValues::new(&[[1; 25]]).message_lengths, [None]);
+    }
+
+    #[test]
+    fn claimed_lengths_are_checked() {
+        let pis = KeccakPublicValues::new(&[padded_state(&message(64))]);
+        let mut pis_shorter = pis.clone();
+        pis_shorter.message_lengths[0] = Some(63);
+        assert_eq!(
+            pis_shorter.check(),
+            Err("message 0 is not padded to 63 bytes".to_string())
+        );
+
+        let pis = KeccakPublicValues::new(&absorb(&message(272)));
+        let mut pis_longer = pis.clone();
+        pis_longer.message_lengths[0] = Some(272 + RATE as u64);
+        assert_eq!(
+            pis_longer.check(),
+            Err("the messages take 4 permutations, not 3".to_string())
+        );
+
+        // the last permutation carries on the first message
+        let mut pis_split = pis;
+        pis_split.num_hashes = 2;
+        pis_split.message_lengths = vec![Some(136), Some(0)];
+        pis_split.digests.push(pis_split.digests[0]);
+        assert_eq!(
+            pis_split.check(),
+            Err("message 1 does not start from the all-zero state".to_string())
+        );
+    }
+
+    #[test]
+    fn padded_state_is_the_single_absorbed_block() {
+        for len in LENGTHS.into_iter().filter(|&len| len < RATE) {
+            let message = message(len);
+            assert_eq!(
+                vec![padded_state(&message)],
+                absorb(&message),
+                "{} bytes",
+                len
+            );
+            assert_eq!(digest(&padded_state(&message)), keccak256(&message));
+        }
+    }
+}
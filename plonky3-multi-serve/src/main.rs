@@ -0,0 +1,84 @@
+use log::info;
+
+use plonky3_config::perm::PermSource;
+use plonky3_fib_serve::backend::FibBackend;
+use plonky3_keccak_serve::backend::KeccakBackend;
+use prover_serve::args::ServeArgs;
+use prover_serve::fri::FriOverrides;
+use prover_serve::hash::HashId;
+use prover_serve::multi::MultiServer;
+use prover_serve::proof::ProofConfig;
+use prover_serve::telemetry;
+
+#[tokio::main]
+async fn main() {
+    // plonky3-multi-serve <input:ip> <input:port> [--field <field>] [--hash <hash>]
+    //                     [--extension-degree <n>] [--dft <dft>]
+    //                     [--seed <u64> | --constants <path>] [fri flags]
+    // parse arg
+    let usage = "Usage: plonky3-multi-serve <input:host> <input:port> [--config <path>] \
+                 [--field babybear|goldilocks|mersenne31] [--hash poseidon2|keccak|blake3] \
+                 [--extension-degree <n>] [--dft radix2-dit-parallel|radix2-bowers|radix2-dit] \
+                 [--seed <u64> | --constants <path>] \
+                 [--log-blowup <n>] [--num-queries <n>] [--proof-of-work-bits <n>] \
+                 [--min-security-bits <n>] [--presets <name>,...|none] [--trace tree|json|off]";
+    let parsed = ServeArgs::from_env().and_then(|mut args| {
+        // both circuits prove over the same field, extension and DFT, and with the same hash if
+        // one is given
+        let hash = args.flags.take::<HashId>("hash")?;
+        let fib_config = ProofConfig::from_flags(
+            &mut args.flags,
+            hash.unwrap_or(plonky3_fib_serve::backend::DEFAULT_HASH),
+        )?;
+        let keccak_config = ProofConfig {
+            hash: hash.unwrap_or(plonky3_keccak_serve::backend::DEFAULT_HASH),
+            ..fib_config
+        };
+        let perm_source = PermSource::from_flags(&mut args.flags)?;
+        // the FRI flags apply to both circuits, on top of their own defaults
+        let fri = FriOverrides::from_flags(&mut args.flags)?;
+        let fib_fri = fri.apply(plonky3_fib_serve::air::DEFAULT_FRI_PARAMS)?;
+        let keccak_fri = fri.apply(plonky3_keccak_serve::DEFAULT_FRI_PARAMS)?;
+        // the configurations only differ in their hash, which does not bound the security
+        let presets = args.presets(&fib_config)?;
+        args.flags.finish()?;
+        Ok((
+            args,
+            fib_config,
+            keccak_config,
+            perm_source,
+            fib_fri,
+            keccak_fri,
+            presets,
+        ))
+    });
+    let (args, fib_config, keccak_config, perm_source, fib_fri, keccak_fri, presets) = match parsed
+    {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            println!("{}\n{}", e, usage);
+            return;
+        }
+    };
+    telemetry::init(args.trace);
+    // setup
+    let fib = FibBackend::new(fib_config, &perm_source, fib_fri, &presets)
+        .expect("unable to set up the fib prover");
+    let keccak = KeccakBackend::new(keccak_config, &perm_source, keccak_fri, &presets)
+        .expect("unable to set up the keccak prover");
+    info!(
+        "Proving fib over {} and keccak over {}.",
+        fib_config, keccak_config
+    );
+    if fib_config.hash == HashId::Poseidon2 || keccak_config.hash == HashId::Poseidon2 {
+        info!("Loaded poseidon2 constants from {:?}.", perm_source);
+    }
+
+    let server = MultiServer::new(&args)
+        .with("fib", fib)
+        .and_then(|server| server.with("keccak", keccak));
+    match server {
+        Ok(server) => server.serve(&args).await,
+        Err(e) => println!("{}\n{}", e, usage),
+    }
+}
@@ -1,5 +1,4 @@
 use rand::Rng;
-use serde::{Deserialize, Serialize};
 use std::{
     fs::File,
     io::{BufReader, Read, Write},
@@ -7,11 +6,8 @@ use std::{
 use tiny_keccak::Hasher;
 
 const WITNESS_GENERATED_MSG: &str = "witness generated";
-
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct MyWitness {
-    pub inputs: Vec<[u64; 25]>,
-}
+/// Size of the blocks the arena hashes one by one.
+const CHUNK_SIZE: usize = 64;
 
 struct ServiceHandler {
     child: std::process::Child,
@@ -32,55 +28,106 @@ fn start_service(service_bin: &str) -> ServiceHandler {
 }
 
 impl ServiceHandler {
-    fn prove(&mut self, inputs: &[u8]) -> Vec<u8> {
+    /// Has the service hash `input` in 64-byte chunks and prove it, returning the public inputs
+    /// (permutation inputs and digests) and the proof.
+    fn prove(&mut self, input: &[u8]) -> (Vec<u8>, Vec<u8>) {
         // connect to the service via http request
         let client = reqwest::blocking::Client::new();
-        // generate ran
-        let converted_input = MyWitness {
-            inputs: inputs
-                .chunks(64)
-                .map(|_| {
-                    // random output
-                    let mut output = [0u64; 25];
-                    for v in &mut output {
-                        *v = rand::thread_rng().gen();
-                    }
-                    output
-                })
-                .collect::<Vec<_>>(),
-        };
         let res = client
-            .post(format!("http://127.0.0.1:{}/prove", self.port))
-            .body(postcard::to_allocvec(&converted_input).unwrap())
+            .post(format!(
+                "http://127.0.0.1:{}/prove/keccak256?chunk_size={}",
+                self.port, CHUNK_SIZE
+            ))
+            .body(input.to_vec())
+            .send()
+            .expect("Failed to send request");
+        assert!(
+            res.status().is_success(),
+            "proving failed: {:?}",
+            res.text()
+        );
+        let envelope = res.bytes().expect("Failed to read response");
+        split_envelope(&envelope).expect("malformed prove response")
+    }
+    fn vk(&mut self) -> Vec<u8> {
+        let client = reqwest::blocking::Client::new();
+        let res = client
+            .get(format!("http://127.0.0.1:{}/vk", self.port))
             .send()
             .expect("Failed to send request");
+        assert!(
+            res.status().is_success(),
+            "fetching the verifying key failed: {:?}",
+            res.text()
+        );
         res.bytes().expect("Failed to read response").to_vec()
     }
-    fn verify(&mut self, public_inputs: &[u8], proof: &[u8]) -> bool {
+    /// Has the service verify the proof `repeat` times in one batch, returning whether every
+    /// verification succeeded, which needs at least one.
+    fn verify(&mut self, public_inputs: &[u8], proof: &[u8], vk: &[u8], repeat: usize) -> bool {
         // connect to the service via http request
         let client = reqwest::blocking::Client::new();
-        let mut body = Vec::new();
-        body.extend_from_slice(&(public_inputs.len() as u64).to_le_bytes());
-        body.extend_from_slice(&(proof.len() as u64).to_le_bytes());
-        body.extend_from_slice(public_inputs);
-        body.extend_from_slice(proof);
+        let mut envelope = Vec::new();
+        envelope.extend_from_slice(&(public_inputs.len() as u64).to_le_bytes());
+        envelope.extend_from_slice(&(proof.len() as u64).to_le_bytes());
+        envelope.extend_from_slice(public_inputs);
+        envelope.extend_from_slice(proof);
+        let mut body = (repeat as u64).to_le_bytes().to_vec();
+        for _ in 0..repeat {
+            body.extend_from_slice(&(envelope.len() as u64).to_le_bytes());
+            body.extend_from_slice(&envelope);
+        }
         let res = client
-            .post(format!("http://127.0.0.1:{}/verify", self.port))
+            .post(format!("http://127.0.0.1:{}/verify/batch", self.port))
+            .header("x-verifying-key", hex::encode(vk))
             .body(body)
             .send()
             .expect("Failed to send request");
+        let status = res.status();
         let res_str = res.text().expect("Failed to read response");
-        res_str == "success"
+        all_succeeded(status.is_success(), &res_str, repeat)
     }
     fn stop(&mut self) {
         self.child.kill().expect("Failed to kill the service");
     }
 }
 
-fn baseline_hasher(inputs: &[u8]) -> Vec<u8> {
-    // chunk the inputs into 64-byte blocks
-    inputs
-        .chunks_exact(64)
+/// Splits `pis_len || proof_len || pis || proof` into public inputs and proof.
+fn split_envelope(envelope: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+    let length_at = |offset: usize| {
+        let bytes = envelope.get(offset..offset + 8)?;
+        usize::try_from(u64::from_le_bytes(bytes.try_into().unwrap())).ok()
+    };
+    let pis_end = length_at(0)?.checked_add(16)?;
+    let proof_end = pis_end.checked_add(length_at(8)?)?;
+    if proof_end != envelope.len() {
+        return None;
+    }
+    Some((
+        envelope[16..pis_end].to_vec(),
+        envelope[pis_end..proof_end].to_vec(),
+    ))
+}
+
+/// Whether a `/verify/batch` reply, `ok` if its status was a success, holds `repeat` verdicts,
+/// at least one, that are all "success".
+fn all_succeeded(ok: bool, body: &str, repeat: usize) -> bool {
+    let verdicts = body.lines().collect::<Vec<_>>();
+    ok && repeat > 0 && verdicts.len() == repeat && verdicts.iter().all(|v| *v == "success")
+}
+
+/// Hashes `inputs` in 64-byte chunks, refusing the inputs the service refuses to prove: empty ones
+/// and ones with a partial chunk at the end.
+fn baseline_hasher(inputs: &[u8]) -> Result<Vec<u8>, String> {
+    if inputs.is_empty() || !inputs.len().is_multiple_of(CHUNK_SIZE) {
+        return Err(format!(
+            "input of {} bytes is not a positive multiple of {} bytes",
+            inputs.len(),
+            CHUNK_SIZE
+        ));
+    }
+    Ok(inputs
+        .chunks_exact(CHUNK_SIZE)
         .flat_map(|x| {
             let mut hasher = tiny_keccak::Keccak::v256();
             let mut output = [0u8; 32];
@@ -88,7 +135,7 @@ fn baseline_hasher(inputs: &[u8]) -> Vec<u8> {
             hasher.finalize(&mut output);
             output
         })
-        .collect()
+        .collect())
 }
 
 fn prove(
@@ -111,17 +158,18 @@ fn prove(
     // STEP 5: Read Input Data
     let input_bytes = read_blob(in_pipe)?;
     // STEP 6: Hash the Data
-    let output = baseline_hasher(&input_bytes);
+    let output = baseline_hasher(&input_bytes)?;
     write_byte_array(out_pipe, &output)?;
     // STEP 7: Output a String to Indicate Witness Generation Finished
     write_string(out_pipe, WITNESS_GENERATED_MSG)?;
     // STEP 8: Output the Proof
-    let proof = service_handler.prove(&input_bytes);
+    // the service hashes the same 64-byte chunks, so the proof attests to the digests of STEP 6
+    let (pis, proof) = service_handler.prove(&input_bytes);
     assert!(!proof.is_empty()); // sanity check
     write_byte_array(out_pipe, &proof)?;
-    let vk = vec![];
+    let vk = service_handler.vk();
+    assert!(!vk.is_empty()); // sanity check
     write_byte_array(out_pipe, &vk)?;
-    let pis = vec![];
     write_byte_array(out_pipe, &pis)?;
 
     out_pipe.flush()?;
@@ -139,13 +187,11 @@ fn verify(
     let mut service_handler = start_service(service_bin);
     // STEP 10: SPJ sends the proof, verification key, and public input to the verifier
     let proof = read_blob(in_pipe)?;
-    let _vk = read_blob(in_pipe)?;
+    let vk = read_blob(in_pipe)?;
     let pis = read_blob(in_pipe)?;
     // STEP 11: Verify the Proof, and send back result
-    let mut result = false;
-    for _ in 0..verifier_repeat_num {
-        result = service_handler.verify(&pis, &proof);
-    }
+    // the service rejects the proof if it was produced under a different configuration
+    let result = service_handler.verify(&pis, &proof, &vk, verifier_repeat_num);
     write_byte_array(out_pipe, &[if result { 0xffu8 } else { 0x00u8 }])?;
     write_byte_array(out_pipe, verifier_repeat_num.to_le_bytes().as_ref())?; // why not number this time?
 
@@ -222,3 +268,26 @@ fn read_blob<R: Read>(reader: &mut R) -> std::io::Result<Vec<u8>> {
     reader.read_exact(&mut buf)?;
     Ok(buf)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn baseline_hasher_takes_whole_chunks_only() {
+        assert_eq!(baseline_hasher(&[7; 2 * CHUNK_SIZE]).unwrap().len(), 64);
+        assert!(baseline_hasher(&[7; 2 * CHUNK_SIZE + 1]).is_err());
+        assert!(baseline_hasher(&[]).is_err());
+    }
+
+    #[test]
+    fn batch_verdicts_need_a_successful_reply() {
+        assert!(all_succeeded(true, "success\nsuccess\n", 2));
+        assert!(!all_succeeded(true, "success\nfailure: ood_mismatch\n", 2));
+        assert!(!all_succeeded(true, "success\n", 2));
+        // nothing verified is not a success
+        assert!(!all_succeeded(true, "", 0));
+        // an error reply whose body happens to read "success"
+        assert!(!all_succeeded(false, "success\n", 1));
+    }
+}
@@ -0,0 +1,144 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::fri::DEFAULT_MIN_SECURITY_BITS;
+use crate::jobs::JobRetention;
+use crate::pool::PoolConfig;
+use crate::preset::Preset;
+use crate::proof::ProofConfig;
+use crate::telemetry::TraceFormat;
+
+/// `--name value` pairs, consumed one by one by whoever understands them.
+#[derive(Debug, Clone, Default)]
+pub struct Flags(BTreeMap<String, String>);
+
+impl Flags {
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut flags = BTreeMap::new();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let name = arg
+                .strip_prefix("--")
+                .ok_or_else(|| format!("unexpected argument {:?}", arg))?;
+            let value = args
+                .next()
+                .ok_or_else(|| format!("missing value for --{}", name))?;
+            if flags.insert(name.to_string(), value.clone()).is_some() {
+                return Err(format!("--{} given more than once", name));
+            }
+        }
+        Ok(Flags(flags))
+    }
+
+    /// Removes `--name` and parses its value, if it was given.
+    pub fn take<T: FromStr>(&mut self, name: &str) -> Result<Option<T>, String>
+    where
+        T::Err: Display,
+    {
+        self.0
+            .remove(name)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|e| format!("invalid value {:?} for --{}: {}", value, name, e))
+            })
+            .transpose()
+    }
+
+    /// Adds the top-level keys of a TOML file as flags, unless already given on the command line.
+    /// `log-blowup = 3` in the file is the same as `--log-blowup 3`.
+    pub fn merge_file(&mut self, path: &Path) -> Result<(), String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("unable to read config file {:?}: {}", path, e))?;
+        let table = contents
+            .parse::<toml::Table>()
+            .map_err(|e| format!("invalid config file {:?}: {}", path, e))?;
+        for (name, value) in table {
+            let value = match value {
+                toml::Value::String(value) => value,
+                toml::Value::Integer(value) => value.to_string(),
+                toml::Value::Float(value) => value.to_string(),
+                toml::Value::Boolean(value) => value.to_string(),
+                _ => return Err(format!("unsupported value for {:?} in {:?}", name, path)),
+            };
+            self.0.entry(name).or_insert(value);
+        }
+        Ok(())
+    }
+
+    /// Fails if any flag was not taken.
+    pub fn finish(&self) -> Result<(), String> {
+        match self.0.keys().next() {
+            Some(name) => Err(format!("unrecognized flag --{}", name)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// `<input:host> <input:port> [--config <path>] [--workers <n>] [--queue-limit <n>]
+/// [--spool-dir <path>] [--job-ttl-secs <n>] [--max-finished-jobs <n>] [--min-security-bits <n>]
+/// [--presets <name>,...] [--trace tree|json|off]
+/// [--name value]...`
+#[derive(Debug, Clone)]
+pub struct ServeArgs {
+    pub host: [u8; 4],
+    pub port: u16,
+    pub pool: PoolConfig,
+    /// Where finished proofs of asynchronous jobs are kept, in memory if unset.
+    pub spool_dir: Option<PathBuf>,
+    pub job_retention: JobRetention,
+    /// Backends with less conjectured security are refused at startup.
+    pub min_security_bits: u64,
+    /// `--presets`, see [`presets`](Self::presets).
+    pub presets: Option<Vec<Preset>>,
+    /// `--trace tree|json|off`, see [`init`](crate::telemetry::init).
+    pub trace: TraceFormat,
+    /// Flags left for the service itself.
+    pub flags: Flags,
+}
+
+impl ServeArgs {
+    /// Parses the arguments following the program name.
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let [host, port, flags @ ..] = args else {
+            return Err("missing host and port".to_string());
+        };
+        let host = host
+            .split('.')
+            .map(|s| s.parse().ok())
+            .collect::<Option<Vec<u8>>>()
+            .and_then(|host| host.try_into().ok())
+            .ok_or_else(|| format!("invalid host {:?}", host))?;
+        let port = port
+            .parse()
+            .map_err(|e| format!("invalid port {:?}: {}", port, e))?;
+        let mut flags = Flags::parse(flags)?;
+        if let Some(config) = flags.take::<PathBuf>("config")? {
+            flags.merge_file(&config)?;
+        }
+        Ok(ServeArgs {
+            host,
+            port,
+            pool: PoolConfig::from_flags(&mut flags)?,
+            spool_dir: flags.take("spool-dir")?,
+            job_retention: JobRetention::from_flags(&mut flags)?,
+            min_security_bits: flags
+                .take("min-security-bits")?
+                .unwrap_or(DEFAULT_MIN_SECURITY_BITS),
+            presets: Preset::from_flags(&mut flags)?,
+            trace: flags.take("trace")?.unwrap_or_default(),
+            flags,
+        })
+    }
+
+    /// Presets prove requests under `config` may pick, see [`Preset::allowed`].
+    pub fn presets(&self, config: &ProofConfig) -> Result<Vec<Preset>, String> {
+        Preset::allowed(self.presets.as_deref(), config, self.min_security_bits)
+    }
+
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(&std::env::args().skip(1).collect::<Vec<_>>())
+    }
+}
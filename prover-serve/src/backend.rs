@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::preset::Preset;
+use crate::vk::VerifyingKey;
+
+/// A proof system that [`serve`](crate::server::serve) can expose over HTTP. Witnesses, public
+/// inputs and proofs travel postcard-serialized.
+pub trait ProofBackend: Send + Sync + 'static {
+    /// Body of `POST /prove`.
+    type Witness: DeserializeOwned + Send + 'static;
+    /// Public-input part of the `POST /verify` body.
+    type PublicInputs: Serialize + DeserializeOwned + Send + 'static;
+    /// Body of the `/prove` reply and proof part of the `/verify` body.
+    type Proof: Serialize + DeserializeOwned + Send + 'static;
+
+    /// Formats served under `POST /prove/<format>`, see [`generate`](Self::generate).
+    const WITNESS_FORMATS: &'static [&'static str] = &[];
+
+    /// Errors describe why the witness cannot be proven and are reported as 400 with a
+    /// "failure: <error>" body. Trace generation and proving times show up in `GET /metrics` when
+    /// wrapped in [`metrics::time`](crate::metrics::time).
+    fn prove(&self, witness: Self::Witness) -> Result<Self::Proof, String>;
+
+    /// Proves with the FRI parameters of `preset`, one of the [`presets`](Self::presets). The
+    /// proof has to carry it for `verify` to check it under the same parameters.
+    fn prove_preset(&self, _witness: Self::Witness, preset: Preset) -> Result<Self::Proof, String> {
+        Err(format!("preset {} is not supported", preset))
+    }
+
+    /// Presets a prove request may pick with `?preset=<name>`, none unless overridden.
+    fn presets(&self) -> Vec<Preset> {
+        vec![]
+    }
+
+    fn verify(&self, pis: &Self::PublicInputs, proof: &Self::Proof) -> Result<(), VerifyError>;
+
+    /// Verifies each of a `POST /verify/batch`, one after the other unless overridden.
+    fn verify_batch(
+        &self,
+        batch: &[(Self::PublicInputs, Self::Proof)],
+    ) -> Vec<Result<(), VerifyError>> {
+        batch
+            .iter()
+            .map(|(pis, proof)| self.verify(pis, proof))
+            .collect()
+    }
+
+    fn verifying_key(&self) -> VerifyingKey;
+
+    /// Builds a witness and the public inputs it proves from a `POST /prove/<format>` body and
+    /// query, for clients that would rather not assemble witnesses themselves. Only called with
+    /// one of the [`WITNESS_FORMATS`](Self::WITNESS_FORMATS), errors are reported as 400.
+    fn generate(
+        &self,
+        format: &str,
+        _query: &HashMap<String, String>,
+        _body: &[u8],
+    ) -> Result<(Self::Witness, Self::PublicInputs), String> {
+        Err(format!("unknown witness format {:?}", format))
+    }
+}
+
+/// Why a `/verify` request failed, reported as the `error` field of the JSON response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerifyErrorKind {
+    /// The `X-Verifying-Key` header does not match the server's key.
+    VerifyingKeyMismatch,
+    /// The length prefixes do not describe the body.
+    LengthFraming,
+    PublicInputDecode,
+    ProofDecode,
+    /// The public inputs decode but are not a valid statement.
+    InvalidPublicInputs,
+    /// The proof is over another field than the one the server verifies.
+    FieldMismatch,
+    /// The proof commits with another hash than the one the server verifies.
+    HashMismatch,
+    /// The proof draws its challenges from another extension than the server's.
+    ExtensionMismatch,
+    /// The proof was made with a preset the server does not allow.
+    PresetNotAllowed,
+    /// The proof does not have the dimensions the configuration expects.
+    ProofShape,
+    /// The constraints evaluated at the out-of-domain point disagree with the quotient.
+    OodMismatch,
+    /// The PCS opening proof, i.e. FRI, does not verify.
+    FriFailure,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyError {
+    pub kind: VerifyErrorKind,
+    pub detail: String,
+}
+
+impl VerifyError {
+    pub fn new(kind: VerifyErrorKind, detail: impl Into<String>) -> Self {
+        VerifyError {
+            kind,
+            detail: detail.into(),
+        }
+    }
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.detail)
+    }
+}
@@ -0,0 +1,48 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::args::Flags;
+
+/// DFT the prover computes its low-degree extensions with, set by `--dft`. It does not change the
+/// proof, only how fast it is made, so proofs verify whichever one they were made with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DftId {
+    #[default]
+    Radix2DitParallel,
+    Radix2Bowers,
+    Radix2Dit,
+}
+
+impl DftId {
+    pub fn from_flags(flags: &mut Flags) -> Result<Option<Self>, String> {
+        flags.take("dft")
+    }
+}
+
+impl FromStr for DftId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "radix2-dit-parallel" => Ok(DftId::Radix2DitParallel),
+            "radix2-bowers" => Ok(DftId::Radix2Bowers),
+            "radix2-dit" => Ok(DftId::Radix2Dit),
+            _ => Err(format!(
+                "expected radix2-dit-parallel, radix2-bowers or radix2-dit, not {:?}",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for DftId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DftId::Radix2DitParallel => write!(f, "Radix2DitParallel"),
+            DftId::Radix2Bowers => write!(f, "Radix2Bowers"),
+            DftId::Radix2Dit => write!(f, "Radix2Dit"),
+        }
+    }
+}
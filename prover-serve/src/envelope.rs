@@ -0,0 +1,286 @@
+use std::fmt;
+
+/// Largest `POST /verify` body accepted.
+pub const MAX_ENVELOPE_BYTES: usize = 64 << 20;
+
+const HEADER_BYTES: usize = 16;
+
+/// The `POST /verify` body: `pis_len || proof_len || pis || proof`, with both lengths as
+/// little-endian u64.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Envelope<'a> {
+    pub pis: &'a [u8],
+    pub proof: &'a [u8],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FramingError {
+    TooLarge {
+        len: usize,
+        max: usize,
+    },
+    MissingHeader {
+        len: usize,
+        header: usize,
+    },
+    /// `16 + pis_len + proof_len` does not fit in a u64.
+    LengthOverflow {
+        pis_len: u64,
+        proof_len: u64,
+    },
+    Truncated {
+        expected: u64,
+        len: usize,
+    },
+    /// An item of a list, with its length prefix, runs past the end of the body.
+    ItemTruncated {
+        index: u64,
+        needed: u64,
+        remaining: usize,
+    },
+    TrailingBytes {
+        expected: u64,
+        len: usize,
+    },
+}
+
+impl fmt::Display for FramingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FramingError::TooLarge { len, max } => {
+                write!(f, "body is {} bytes, more than the maximum of {}", len, max)
+            }
+            FramingError::MissingHeader { len, header } => write!(
+                f,
+                "body is {} bytes, shorter than the {}-byte length header",
+                len, header
+            ),
+            FramingError::ItemTruncated {
+                index,
+                needed,
+                remaining,
+            } => write!(
+                f,
+                "item {} needs {} bytes but only {} remain",
+                index, needed, remaining
+            ),
+            FramingError::LengthOverflow { pis_len, proof_len } => {
+                write!(f, "length prefixes {} and {} overflow", pis_len, proof_len)
+            }
+            FramingError::Truncated { expected, len } => write!(
+                f,
+                "length prefixes announce a {}-byte body but it is {} bytes",
+                expected, len
+            ),
+            FramingError::TrailingBytes { expected, len } => write!(
+                f,
+                "{} trailing bytes after the {}-byte body the length prefixes announce",
+                *len as u64 - expected,
+                expected
+            ),
+        }
+    }
+}
+
+impl<'a> Envelope<'a> {
+    /// Splits `body` into public inputs and proof, which must fill it exactly.
+    pub fn parse(body: &'a [u8], max_len: usize) -> Result<Self, FramingError> {
+        let len = body.len();
+        if len > max_len {
+            return Err(FramingError::TooLarge { len, max: max_len });
+        }
+        let (header, rest) =
+            body.split_first_chunk::<HEADER_BYTES>()
+                .ok_or(FramingError::MissingHeader {
+                    len,
+                    header: HEADER_BYTES,
+                })?;
+        let (pis_len, proof_len) = header.split_at(8);
+        let pis_len = u64::from_le_bytes(pis_len.try_into().unwrap());
+        let proof_len = u64::from_le_bytes(proof_len.try_into().unwrap());
+        let expected = pis_len
+            .checked_add(proof_len)
+            .and_then(|payload| payload.checked_add(HEADER_BYTES as u64))
+            .ok_or(FramingError::LengthOverflow { pis_len, proof_len })?;
+        if expected > len as u64 {
+            return Err(FramingError::Truncated { expected, len });
+        }
+        if expected < len as u64 {
+            return Err(FramingError::TrailingBytes { expected, len });
+        }
+        // both fit in the body now, so they fit in a usize
+        let (pis, proof) = rest.split_at(pis_len as usize);
+        Ok(Envelope { pis, proof })
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut body = Vec::with_capacity(HEADER_BYTES + self.pis.len() + self.proof.len());
+        body.extend_from_slice(&(self.pis.len() as u64).to_le_bytes());
+        body.extend_from_slice(&(self.proof.len() as u64).to_le_bytes());
+        body.extend_from_slice(self.pis);
+        body.extend_from_slice(self.proof);
+        body
+    }
+}
+
+/// Splits a `POST /prove/batch` or `POST /verify/batch` body, `count || (len || item)*` with all
+/// lengths little-endian u64, into its items, which must fill it exactly.
+pub fn parse_list(body: &[u8], max_len: usize) -> Result<Vec<&[u8]>, FramingError> {
+    let len = body.len();
+    if len > max_len {
+        return Err(FramingError::TooLarge { len, max: max_len });
+    }
+    let (count, mut rest) = body
+        .split_first_chunk::<8>()
+        .ok_or(FramingError::MissingHeader { len, header: 8 })?;
+    let count = u64::from_le_bytes(*count);
+    // every item takes at least 8 bytes, so this runs at most `len / 8` times
+    let mut items = vec![];
+    for index in 0..count {
+        let truncated = |needed| FramingError::ItemTruncated {
+            index,
+            needed,
+            remaining: rest.len(),
+        };
+        let (item_len, after_len) = rest.split_first_chunk::<8>().ok_or(truncated(8))?;
+        let item_len = u64::from_le_bytes(*item_len);
+        let item_len = usize::try_from(item_len)
+            .ok()
+            .filter(|&item_len| item_len <= after_len.len())
+            .ok_or(truncated(item_len.saturating_add(8)))?;
+        let (item, after_item) = after_len.split_at(item_len);
+        items.push(item);
+        rest = after_item;
+    }
+    if !rest.is_empty() {
+        return Err(FramingError::TrailingBytes {
+            expected: (len - rest.len()) as u64,
+            len,
+        });
+    }
+    Ok(items)
+}
+
+pub fn encode_list<'a>(items: impl ExactSizeIterator<Item = &'a [u8]>) -> Vec<u8> {
+    let mut body = (items.len() as u64).to_le_bytes().to_vec();
+    for item in items {
+        body.extend_from_slice(&(item.len() as u64).to_le_bytes());
+        body.extend_from_slice(item);
+    }
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    const MAX: usize = 4096;
+
+    fn bytes(max_len: usize) -> impl Strategy<Value = Vec<u8>> {
+        prop::collection::vec(any::<u8>(), 0..max_len)
+    }
+
+    proptest! {
+        #[test]
+        fn arbitrary_bodies_parse_or_fail_cleanly(body in bytes(MAX)) {
+            if let Ok(envelope) = Envelope::parse(&body, MAX) {
+                prop_assert_eq!(
+                    HEADER_BYTES + envelope.pis.len() + envelope.proof.len(),
+                    body.len()
+                );
+                prop_assert_eq!(envelope.encode(), body);
+            }
+        }
+
+        #[test]
+        fn arbitrary_headers_parse_or_fail_cleanly(
+            pis_len in any::<u64>(),
+            proof_len in any::<u64>(),
+            payload in bytes(MAX - HEADER_BYTES),
+        ) {
+            let mut body = pis_len.to_le_bytes().to_vec();
+            body.extend_from_slice(&proof_len.to_le_bytes());
+            body.extend_from_slice(&payload);
+            let parsed = Envelope::parse(&body, MAX);
+            if pis_len as u128 + proof_len as u128 == payload.len() as u128 {
+                prop_assert!(parsed.is_ok());
+            } else {
+                prop_assert!(parsed.is_err());
+            }
+        }
+
+        #[test]
+        fn encoded_envelopes_round_trip(pis in bytes(256), proof in bytes(1024)) {
+            let envelope = Envelope { pis: &pis, proof: &proof };
+            let body = envelope.encode();
+            prop_assert_eq!(Envelope::parse(&body, MAX), Ok(envelope));
+        }
+
+        #[test]
+        fn trailing_bytes_are_rejected(
+            pis in bytes(256),
+            proof in bytes(1024),
+            extra in 1..64usize,
+        ) {
+            let mut body = Envelope { pis: &pis, proof: &proof }.encode();
+            body.resize(body.len() + extra, 0);
+            let is_trailing = matches!(
+                Envelope::parse(&body, MAX),
+                Err(FramingError::TrailingBytes { .. })
+            );
+            prop_assert!(is_trailing);
+        }
+
+        #[test]
+        fn truncated_bodies_are_rejected(
+            pis in bytes(256),
+            proof in bytes(1024),
+            cut in 1..64usize,
+        ) {
+            let mut body = Envelope { pis: &pis, proof: &proof }.encode();
+            body.truncate(body.len().saturating_sub(cut));
+            let is_truncated = matches!(
+                Envelope::parse(&body, MAX),
+                Err(FramingError::Truncated { .. } | FramingError::MissingHeader { .. })
+            );
+            prop_assert!(is_truncated);
+        }
+
+        #[test]
+        fn arbitrary_lists_parse_or_fail_cleanly(body in bytes(MAX)) {
+            if let Ok(items) = parse_list(&body, MAX) {
+                prop_assert_eq!(encode_list(items.into_iter()), body);
+            }
+        }
+
+        #[test]
+        fn encoded_lists_round_trip(items in prop::collection::vec(bytes(64), 0..32)) {
+            let body = encode_list(items.iter().map(Vec::as_slice));
+            let parsed = parse_list(&body, MAX).unwrap();
+            prop_assert_eq!(parsed, items.iter().map(Vec::as_slice).collect::<Vec<_>>());
+        }
+
+        #[test]
+        fn altered_lists_are_rejected(
+            items in prop::collection::vec(bytes(64), 1..32),
+            cut in 1..8usize,
+        ) {
+            let body = encode_list(items.iter().map(Vec::as_slice));
+            prop_assert!(parse_list(&body[..body.len() - cut.min(body.len())], MAX).is_err());
+            let mut longer = body.clone();
+            longer.push(0);
+            prop_assert!(parse_list(&longer, MAX).is_err());
+        }
+
+        #[test]
+        fn oversized_bodies_are_rejected(body in bytes(256), max in 0..256usize) {
+            prop_assume!(body.len() > max);
+            prop_assert_eq!(
+                Envelope::parse(&body, max),
+                Err(FramingError::TooLarge { len: body.len(), max })
+            );
+        }
+    }
+}
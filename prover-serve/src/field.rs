@@ -0,0 +1,75 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::args::Flags;
+
+/// Base field a backend proves over, set by `--field`. Proofs carry it, so that a proof over
+/// one field is never checked under the configuration of another.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FieldId {
+    #[default]
+    BabyBear,
+    Goldilocks,
+    /// Proven with the circle PCS instead of FRI over a two-adic subgroup.
+    Mersenne31,
+}
+
+impl FieldId {
+    pub const ALL: [FieldId; 3] = [FieldId::BabyBear, FieldId::Goldilocks, FieldId::Mersenne31];
+
+    pub fn from_flags(flags: &mut Flags) -> Result<Self, String> {
+        Ok(flags.take("field")?.unwrap_or_default())
+    }
+
+    /// Degrees of the extensions challenges can be drawn from, the default first. Mersenne31's is
+    /// a binomial extension of the base field, like the others.
+    pub fn extension_degrees(&self) -> &'static [u8] {
+        match self {
+            FieldId::BabyBear => &[4, 5],
+            FieldId::Goldilocks => &[2],
+            FieldId::Mersenne31 => &[3],
+        }
+    }
+
+    /// Bits of the field order, rounded up.
+    pub fn bits(&self) -> u64 {
+        match self {
+            FieldId::BabyBear | FieldId::Mersenne31 => 31,
+            FieldId::Goldilocks => 64,
+        }
+    }
+
+    /// Whether the PCS extends traces with a DFT, which the circle PCS does not.
+    pub fn has_dft(&self) -> bool {
+        *self != FieldId::Mersenne31
+    }
+}
+
+impl FromStr for FieldId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "babybear" => Ok(FieldId::BabyBear),
+            "goldilocks" => Ok(FieldId::Goldilocks),
+            "mersenne31" => Ok(FieldId::Mersenne31),
+            _ => Err(format!(
+                "expected babybear, goldilocks or mersenne31, not {:?}",
+                s
+            )),
+        }
+    }
+}
+
+/// The name used in verifying keys and `GET /config`.
+impl fmt::Display for FieldId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldId::BabyBear => write!(f, "BabyBear"),
+            FieldId::Goldilocks => write!(f, "Goldilocks"),
+            FieldId::Mersenne31 => write!(f, "Mersenne31"),
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use serde::{Deserialize, Serialize};
+
+use crate::args::Flags;
+use crate::vk::VerifyingKey;
+
+/// Refuse to serve configurations with less conjectured security than this, unless overridden
+/// with `--min-security-bits`.
+pub const DEFAULT_MIN_SECURITY_BITS: u64 = 64;
+
+/// FRI parameters of a backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FriParams {
+    pub log_blowup: usize,
+    pub num_queries: usize,
+    pub proof_of_work_bits: usize,
+}
+
+/// FRI parameters given with `--log-blowup`, `--num-queries` and `--proof-of-work-bits`, on top
+/// of each backend's defaults.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FriOverrides {
+    pub log_blowup: Option<usize>,
+    pub num_queries: Option<usize>,
+    pub proof_of_work_bits: Option<usize>,
+}
+
+impl FriOverrides {
+    pub fn from_flags(flags: &mut Flags) -> Result<Self, String> {
+        Ok(FriOverrides {
+            log_blowup: flags.take("log-blowup")?,
+            num_queries: flags.take("num-queries")?,
+            proof_of_work_bits: flags.take("proof-of-work-bits")?,
+        })
+    }
+
+    pub fn apply(&self, defaults: FriParams) -> Result<FriParams, String> {
+        let params = FriParams {
+            log_blowup: self.log_blowup.unwrap_or(defaults.log_blowup),
+            num_queries: self.num_queries.unwrap_or(defaults.num_queries),
+            proof_of_work_bits: self
+                .proof_of_work_bits
+                .unwrap_or(defaults.proof_of_work_bits),
+        };
+        if params.log_blowup == 0 || params.num_queries == 0 {
+            return Err("--log-blowup and --num-queries must be positive".to_string());
+        }
+        Ok(params)
+    }
+}
+
+impl FriParams {
+    /// Each query is conjectured to give `log_blowup` bits, on top of the grinding bits, up to the
+    /// `challenge_bits` of the extension challenges are drawn from.
+    pub fn conjectured_security_bits(&self, challenge_bits: u64) -> u64 {
+        conjectured_security_bits(
+            self.log_blowup as u64,
+            self.num_queries as u64,
+            self.proof_of_work_bits as u64,
+            challenge_bits,
+        )
+    }
+}
+
+pub(crate) fn conjectured_security_bits(
+    log_blowup: u64,
+    num_queries: u64,
+    proof_of_work_bits: u64,
+    challenge_bits: u64,
+) -> u64 {
+    log_blowup
+        .saturating_mul(num_queries)
+        .saturating_add(proof_of_work_bits)
+        .min(challenge_bits)
+}
+
+/// Fails for backends whose configuration falls short of `min_security_bits`.
+pub(crate) fn check_security(
+    name: &str,
+    vk: &VerifyingKey,
+    min_security_bits: u64,
+) -> Result<(), String> {
+    let bits = vk.conjectured_security_bits();
+    if bits < min_security_bits {
+        return Err(format!(
+            "{} has {} bits of conjectured security (log_blowup {}, num_queries {}, \
+             proof_of_work_bits {}, {}-bit challenges), below the minimum of {}",
+            name,
+            bits,
+            vk.log_blowup,
+            vk.num_queries,
+            vk.proof_of_work_bits,
+            vk.challenge_bits(),
+            min_security_bits
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::FieldId;
+    use crate::hash::HashId;
+    use crate::preset::Preset;
+    use crate::proof::ProofConfig;
+
+    /// 200 bits from queries alone, more than any extension has.
+    const PLENTY: FriParams = FriParams {
+        log_blowup: 2,
+        num_queries: 100,
+        proof_of_work_bits: 0,
+    };
+
+    fn config(field: FieldId, extension_degree: u8) -> ProofConfig {
+        ProofConfig {
+            extension_degree,
+            ..ProofConfig::new(field, HashId::Keccak)
+        }
+    }
+
+    fn vk(config: &ProofConfig, fri: FriParams) -> VerifyingKey {
+        VerifyingKey {
+            air: "TestAir".to_string(),
+            field: config.field.to_string(),
+            extension_degree: config.extension_degree as u64,
+            trace_width: 1,
+            log_blowup: fri.log_blowup as u64,
+            num_queries: fri.num_queries as u64,
+            proof_of_work_bits: fri.proof_of_work_bits as u64,
+            hash: config.hash.to_string(),
+            hash_constants_digest: [0; 32],
+        }
+    }
+
+    #[test]
+    fn security_is_capped_by_the_challenge_field() {
+        for (field, extension_degree, bits) in [
+            (FieldId::BabyBear, 4, 124),
+            (FieldId::BabyBear, 5, 155),
+            (FieldId::Goldilocks, 2, 128),
+            (FieldId::Mersenne31, 3, 93),
+        ] {
+            assert!(field.extension_degrees().contains(&extension_degree));
+            let config = config(field, extension_degree);
+            assert_eq!(config.challenge_bits(), bits);
+            assert_eq!(PLENTY.conjectured_security_bits(bits), bits);
+            assert_eq!(vk(&config, PLENTY).conjectured_security_bits(), bits);
+        }
+    }
+
+    #[test]
+    fn security_below_the_cap_is_unchanged() {
+        let fri = FriParams {
+            log_blowup: 1,
+            num_queries: 60,
+            proof_of_work_bits: 16,
+        };
+        for field in FieldId::ALL {
+            let config = ProofConfig::new(field, HashId::Keccak);
+            assert_eq!(vk(&config, fri).conjectured_security_bits(), 76);
+        }
+    }
+
+    #[test]
+    fn check_security_applies_the_cap() {
+        let mersenne31 = vk(&config(FieldId::Mersenne31, 3), PLENTY);
+        assert!(check_security("TestAir", &mersenne31, 93).is_ok());
+        let e = check_security("TestAir", &mersenne31, 100).unwrap_err();
+        assert!(e.contains("93 bits"), "{}", e);
+        assert!(e.contains("93-bit challenges"), "{}", e);
+
+        let babybear = vk(&config(FieldId::BabyBear, 5), PLENTY);
+        assert!(check_security("TestAir", &babybear, 128).is_ok());
+    }
+
+    #[test]
+    fn presets_are_filtered_by_the_capped_security() {
+        let mersenne31 = config(FieldId::Mersenne31, 3);
+        let babybear = config(FieldId::BabyBear, 4);
+        // high-security has 128 bits from its queries, more than Mersenne31's challenges
+        assert_eq!(Preset::allowed(None, &mersenne31, 100).unwrap(), vec![]);
+        assert_eq!(
+            Preset::allowed(None, &babybear, 100).unwrap(),
+            Preset::ALL.to_vec()
+        );
+        assert_eq!(Preset::allowed(None, &babybear, 125).unwrap(), vec![]);
+        assert_eq!(
+            Preset::allowed(None, &config(FieldId::Goldilocks, 2), 128).unwrap(),
+            vec![Preset::HighSecurity]
+        );
+        assert!(Preset::allowed(Some(&[Preset::HighSecurity]), &mersenne31, 94).is_err());
+        assert_eq!(
+            Preset::allowed(Some(&[Preset::Fast]), &mersenne31, 64).unwrap(),
+            vec![Preset::Fast]
+        );
+    }
+}
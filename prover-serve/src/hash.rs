@@ -0,0 +1,45 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::args::Flags;
+
+/// Hash the Merkle commitments are made with, set by `--hash`. The challenger follows it: a
+/// duplex sponge over the Poseidon2 permutation, or a byte hash chain over Keccak or Blake3.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum HashId {
+    Poseidon2,
+    Keccak,
+    Blake3,
+}
+
+impl HashId {
+    /// Takes `--hash`, falling back to `default`, the hash the backend was first written for.
+    pub fn from_flags(flags: &mut Flags, default: HashId) -> Result<Self, String> {
+        Ok(flags.take("hash")?.unwrap_or(default))
+    }
+}
+
+impl FromStr for HashId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "poseidon2" => Ok(HashId::Poseidon2),
+            "keccak" => Ok(HashId::Keccak),
+            "blake3" => Ok(HashId::Blake3),
+            _ => Err(format!("expected poseidon2, keccak or blake3, not {:?}", s)),
+        }
+    }
+}
+
+impl fmt::Display for HashId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HashId::Poseidon2 => write!(f, "Poseidon2"),
+            HashId::Keccak => write!(f, "Keccak256"),
+            HashId::Blake3 => write!(f, "Blake3"),
+        }
+    }
+}
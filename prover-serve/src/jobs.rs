@@ -0,0 +1,375 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use log::info;
+use serde::Serialize;
+
+use crate::args::Flags;
+use crate::pool::QueuedJob;
+
+/// How long finished jobs are kept, set by `--job-ttl-secs` and `--max-finished-jobs`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JobRetention {
+    /// Finished jobs older than this are forgotten.
+    pub ttl: Duration,
+    /// Beyond this many finished jobs, the oldest are forgotten.
+    pub max_finished: usize,
+}
+
+impl Default for JobRetention {
+    fn default() -> Self {
+        JobRetention {
+            ttl: Duration::from_secs(60 * 60),
+            max_finished: 1024,
+        }
+    }
+}
+
+impl JobRetention {
+    pub fn from_flags(flags: &mut Flags) -> Result<Self, String> {
+        let default = JobRetention::default();
+        Ok(JobRetention {
+            ttl: flags
+                .take("job-ttl-secs")?
+                .map_or(default.ttl, Duration::from_secs),
+            max_finished: flags
+                .take("max-finished-jobs")?
+                .unwrap_or(default.max_finished),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    fn is_finished(self) -> bool {
+        matches!(
+            self,
+            JobState::Done | JobState::Failed | JobState::Cancelled
+        )
+    }
+}
+
+/// Body of `GET /jobs/<id>`.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobStatus {
+    pub id: String,
+    pub state: JobState,
+    pub submitted_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Why the job failed.
+    pub error: Option<String>,
+}
+
+pub enum ProofLookup {
+    Unknown,
+    NotReady(JobStatus),
+    Ready(Vec<u8>),
+}
+
+struct Job {
+    status: JobStatus,
+    /// Serialized proof of a done job, unless it was spooled to disk.
+    proof: Option<Vec<u8>>,
+    /// The work of a queued job, until a worker starts it.
+    queued: Option<QueuedJob>,
+}
+
+/// Asynchronous proof jobs of one backend. Job state lives in memory until the job is deleted or
+/// outlives its [`JobRetention`]; with a spool directory, finished proofs are kept on disk instead.
+pub struct JobStore {
+    jobs: Mutex<HashMap<String, Job>>,
+    spool_dir: Option<PathBuf>,
+    retention: JobRetention,
+}
+
+impl JobStore {
+    pub fn new(spool_dir: Option<PathBuf>, retention: JobRetention) -> std::io::Result<Self> {
+        if let Some(spool_dir) = &spool_dir {
+            std::fs::create_dir_all(spool_dir)?;
+        }
+        Ok(JobStore {
+            jobs: Mutex::new(HashMap::new()),
+            spool_dir,
+            retention,
+        })
+    }
+
+    /// Registers a new queued job.
+    pub fn create(&self) -> JobStatus {
+        let mut jobs = self.jobs();
+        let id = loop {
+            let id = format!("{:016x}", rand::random::<u64>());
+            if !jobs.contains_key(&id) {
+                break id;
+            }
+        };
+        let status = JobStatus {
+            id: id.clone(),
+            state: JobState::Queued,
+            submitted_at: Utc::now(),
+            started_at: None,
+            finished_at: None,
+            error: None,
+        };
+        jobs.insert(
+            id,
+            Job {
+                status: status.clone(),
+                proof: None,
+                queued: None,
+            },
+        );
+        status
+    }
+
+    /// Keeps the work of a queued job, so that cancelling it takes the work out of the queue.
+    pub fn queued(&self, id: &str, queued: QueuedJob) {
+        let mut jobs = self.jobs();
+        if let Some(job) = jobs
+            .get_mut(id)
+            .filter(|job| job.status.state == JobState::Queued)
+        {
+            job.queued = Some(queued);
+        }
+    }
+
+    pub fn status(&self, id: &str) -> Option<JobStatus> {
+        let jobs = self.jobs();
+        jobs.get(id).map(|job| job.status.clone())
+    }
+
+    /// Marks a queued job as running, or returns false if it was cancelled in the meantime.
+    pub fn start(&self, id: &str) -> bool {
+        let mut jobs = self.jobs();
+        match jobs.get_mut(id) {
+            Some(job) if job.status.state == JobState::Queued => {
+                job.status.state = JobState::Running;
+                job.status.started_at = Some(Utc::now());
+                job.queued = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Records the outcome of a running job. Results of jobs cancelled while running are dropped.
+    pub fn finish(&self, id: &str, result: Result<Vec<u8>, String>) {
+        let result = result.and_then(|proof| match &self.spool_dir {
+            Some(_) => std::fs::write(self.spool_path(id), &proof)
+                .map(|_| None)
+                .map_err(|e| format!("unable to spool proof: {}", e)),
+            None => Ok(Some(proof)),
+        });
+        let mut jobs = self.jobs();
+        let Some(job) = jobs
+            .get_mut(id)
+            .filter(|job| job.status.state == JobState::Running)
+        else {
+            info!("Discarding result of cancelled job {}.", id);
+            self.remove_spooled(id);
+            return;
+        };
+        job.status.finished_at = Some(Utc::now());
+        match result {
+            Ok(proof) => {
+                job.status.state = JobState::Done;
+                job.proof = proof;
+            }
+            Err(e) => {
+                job.status.state = JobState::Failed;
+                job.status.error = Some(e);
+            }
+        }
+    }
+
+    pub fn proof(&self, id: &str) -> ProofLookup {
+        let jobs = self.jobs();
+        match jobs.get(id) {
+            None => ProofLookup::Unknown,
+            Some(job) if job.status.state != JobState::Done => {
+                ProofLookup::NotReady(job.status.clone())
+            }
+            Some(job) => match &job.proof {
+                Some(proof) => ProofLookup::Ready(proof.clone()),
+                None => match std::fs::read(self.spool_path(id)) {
+                    Ok(proof) => ProofLookup::Ready(proof),
+                    Err(e) => {
+                        let mut status = job.status.clone();
+                        status.state = JobState::Failed;
+                        status.error = Some(format!("unable to read spooled proof: {}", e));
+                        ProofLookup::NotReady(status)
+                    }
+                },
+            },
+        }
+    }
+
+    /// Cancels a queued or running job, or deletes a finished one along with its proof. Returns
+    /// the job's status after cancellation, or before deletion.
+    ///
+    /// A queued job is taken out of the queue, freeing its slot. A running one is not stopped: its
+    /// worker stays busy until the proof is done, and only then drops it.
+    pub fn cancel(&self, id: &str) -> Option<JobStatus> {
+        let mut jobs = self.jobs();
+        let job = jobs.get_mut(id)?;
+        if job.status.state.is_finished() {
+            let job = jobs.remove(id).unwrap();
+            self.remove_spooled(id);
+            return Some(job.status);
+        }
+        if let Some(queued) = job.queued.take() {
+            if queued.cancel() {
+                info!("Took cancelled job {} out of the queue.", id);
+            }
+        }
+        job.status.state = JobState::Cancelled;
+        job.status.finished_at = Some(Utc::now());
+        Some(job.status.clone())
+    }
+
+    /// Forgets a job that never made it to a worker.
+    pub fn remove(&self, id: &str) {
+        self.jobs().remove(id);
+    }
+
+    /// The jobs, without the finished ones past their retention.
+    fn jobs(&self) -> MutexGuard<'_, HashMap<String, Job>> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let now = Utc::now();
+        let mut finished = jobs
+            .values()
+            .filter(|job| job.status.state.is_finished())
+            .filter_map(|job| Some((job.status.finished_at?, job.status.id.clone())))
+            .collect::<Vec<_>>();
+        finished.sort();
+        let excess = finished.len().saturating_sub(self.retention.max_finished);
+        for (i, (finished_at, id)) in finished.into_iter().enumerate() {
+            let expired = (now - finished_at)
+                .to_std()
+                .is_ok_and(|age| age > self.retention.ttl);
+            if i >= excess && !expired {
+                // the rest finished later
+                break;
+            }
+            info!("Forgetting finished job {}.", id);
+            jobs.remove(&id);
+            self.remove_spooled(&id);
+        }
+        jobs
+    }
+
+    fn spool_path(&self, id: &str) -> PathBuf {
+        let spool_dir = self.spool_dir.as_ref().expect("no spool directory");
+        spool_dir.join(format!("{}.proof", id))
+    }
+
+    fn remove_spooled(&self, id: &str) {
+        if self.spool_dir.is_some() {
+            let _ = std::fs::remove_file(self.spool_path(id));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{mpsc, Arc};
+
+    use super::*;
+    use crate::pool::{PoolConfig, WorkerPool};
+
+    fn store(ttl: Duration, max_finished: usize) -> JobStore {
+        JobStore::new(None, JobRetention { ttl, max_finished }).unwrap()
+    }
+
+    fn finished(jobs: &JobStore) -> String {
+        let id = jobs.create().id;
+        assert!(jobs.start(&id));
+        jobs.finish(&id, Ok(vec![1, 2, 3]));
+        id
+    }
+
+    #[test]
+    fn oldest_finished_jobs_are_evicted_beyond_the_limit() {
+        let jobs = store(Duration::from_secs(60), 2);
+        let ids = (0..3).map(|_| finished(&jobs)).collect::<Vec<_>>();
+        let running = jobs.create().id;
+        assert!(jobs.start(&running));
+        assert!(jobs.status(&ids[0]).is_none());
+        assert!(jobs.status(&ids[1]).is_some());
+        assert!(jobs.status(&ids[2]).is_some());
+        assert_eq!(jobs.status(&running).unwrap().state, JobState::Running);
+    }
+
+    #[test]
+    fn finished_jobs_expire() {
+        let jobs = store(Duration::ZERO, 16);
+        let id = finished(&jobs);
+        let queued = jobs.create().id;
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(jobs.status(&id).is_none());
+        assert!(matches!(jobs.proof(&id), ProofLookup::Unknown));
+        assert_eq!(jobs.status(&queued).unwrap().state, JobState::Queued);
+    }
+
+    #[test]
+    fn cancelling_a_queued_job_drops_its_work() {
+        let pool = WorkerPool::new(PoolConfig {
+            workers: 1,
+            queue_limit: 1,
+        });
+        let (release, blocked) = mpsc::channel::<()>();
+        pool.submit_cancellable("blocker".to_string(), move || {
+            let _ = blocked.recv();
+        })
+        .unwrap();
+
+        let jobs = Arc::new(store(Duration::from_secs(60), 16));
+        let id = jobs.create().id;
+        let ran = Arc::new(AtomicBool::new(false));
+        let queued = {
+            let (jobs, id, ran) = (jobs.clone(), id.clone(), ran.clone());
+            pool.submit_cancellable("job".to_string(), move || {
+                if jobs.start(&id) {
+                    ran.store(true, Ordering::SeqCst);
+                }
+            })
+            .unwrap()
+        };
+        jobs.queued(&id, queued);
+        assert_eq!(pool.in_flight(), 2);
+
+        assert_eq!(jobs.cancel(&id).unwrap().state, JobState::Cancelled);
+        assert_eq!(pool.in_flight(), 1);
+        assert_eq!(Arc::strong_count(&ran), 1);
+
+        release.send(()).unwrap();
+        while pool.in_flight() > 0 {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert!(!ran.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn cancelling_a_running_job_discards_its_result() {
+        let jobs = store(Duration::from_secs(60), 16);
+        let id = jobs.create().id;
+        assert!(jobs.start(&id));
+        assert_eq!(jobs.cancel(&id).unwrap().state, JobState::Cancelled);
+        jobs.finish(&id, Ok(vec![1]));
+        assert!(matches!(jobs.proof(&id), ProofLookup::NotReady(_)));
+    }
+}
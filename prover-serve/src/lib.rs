@@ -0,0 +1,20 @@
+//! Shared HTTP plumbing for the prover services: argument parsing, the `/ready`, `/vk`, `/prove`
+//! and `/verify` endpoints, and the [`ProofBackend`](backend::ProofBackend) trait a proof system
+//! implements to be served by them.
+
+pub mod args;
+pub mod backend;
+pub mod dft;
+pub mod envelope;
+pub mod field;
+pub mod fri;
+pub mod hash;
+pub mod jobs;
+pub mod metrics;
+pub mod multi;
+pub mod pool;
+pub mod preset;
+pub mod proof;
+pub mod server;
+pub mod telemetry;
+pub mod vk;
@@ -0,0 +1,340 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use warp::http::StatusCode;
+
+/// Upper bounds of the latency buckets, in seconds.
+const SECONDS_BUCKETS: [f64; 15] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0,
+];
+
+/// Upper bounds of the size buckets, in bytes: powers of 4 from 1 KiB to 64 MiB.
+const BYTES_BUCKETS: [f64; 9] = [
+    1024.0, 4096.0, 16384.0, 65536.0, 262144.0, 1048576.0, 4194304.0, 16777216.0, 67108864.0,
+];
+
+/// How a `/prove` or `/verify` request ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Outcome {
+    /// A proof, or a successful verification.
+    Success,
+    /// A bad request, a witness that cannot be proven or a proof that does not verify.
+    Failure,
+    /// Turned away because the worker pool was full.
+    Busy,
+    /// A panic or a lost worker.
+    Error,
+}
+
+impl Outcome {
+    pub fn from_status(status: StatusCode) -> Self {
+        match status {
+            StatusCode::SERVICE_UNAVAILABLE => Outcome::Busy,
+            status if status.is_server_error() => Outcome::Error,
+            status if status.is_success() => Outcome::Success,
+            _ => Outcome::Failure,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Outcome::Success => "success",
+            Outcome::Failure => "failure",
+            Outcome::Busy => "busy",
+            Outcome::Error => "error",
+        }
+    }
+}
+
+/// Part of a request whose latency is tracked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Phase {
+    /// Building the trace, from a witness or from `POST /prove/<format>` input.
+    TraceGeneration,
+    /// Proving the trace.
+    Proving,
+    /// Serializing the proof.
+    Serialization,
+    Verification,
+}
+
+impl Phase {
+    fn label(self) -> &'static str {
+        match self {
+            Phase::TraceGeneration => "trace_generation",
+            Phase::Proving => "proving",
+            Phase::Serialization => "serialization",
+            Phase::Verification => "verification",
+        }
+    }
+}
+
+struct Histogram {
+    bounds: &'static [f64],
+    /// Observations per bucket, the last one above every bound.
+    counts: Vec<u64>,
+    sum: f64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Histogram {
+            bounds,
+            counts: vec![0; bounds.len() + 1],
+            sum: 0.0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        let bucket = self.bounds.partition_point(|&bound| bound < value);
+        self.counts[bucket] += 1;
+        self.sum += value;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let mut cumulative = 0;
+        for (bound, count) in self.bounds.iter().zip(&self.counts) {
+            cumulative += count;
+            let _ = writeln!(
+                out,
+                "{}_bucket{{{},le=\"{}\"}} {}",
+                name, labels, bound, cumulative
+            );
+        }
+        cumulative += self.counts[self.bounds.len()];
+        let _ = writeln!(
+            out,
+            "{}_bucket{{{},le=\"+Inf\"}} {}",
+            name, labels, cumulative
+        );
+        let _ = writeln!(out, "{}_sum{{{}}} {}", name, labels, self.sum);
+        let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, cumulative);
+    }
+}
+
+struct Registry {
+    requests: BTreeMap<(String, &'static str, Outcome), u64>,
+    phases: BTreeMap<(String, Phase), Histogram>,
+    proof_bytes: BTreeMap<String, Histogram>,
+    witness_bytes: BTreeMap<String, Histogram>,
+}
+
+/// Shared by every backend of the process, which tell themselves apart with the `air` label.
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+    requests: BTreeMap::new(),
+    phases: BTreeMap::new(),
+    proof_bytes: BTreeMap::new(),
+    witness_bytes: BTreeMap::new(),
+});
+
+/// Counts a request to `endpoint`, e.g. "prove" or "verify_batch".
+pub fn record_request(air: &str, endpoint: &'static str, outcome: Outcome) {
+    let mut registry = REGISTRY.lock().unwrap();
+    *registry
+        .requests
+        .entry((air.to_string(), endpoint, outcome))
+        .or_default() += 1;
+}
+
+/// Runs `f`, recording how long it took as `phase` of proving or verifying for `air`.
+pub fn time<T>(air: &str, phase: Phase, f: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = f();
+    let elapsed = start.elapsed().as_secs_f64();
+    REGISTRY
+        .lock()
+        .unwrap()
+        .phases
+        .entry((air.to_string(), phase))
+        .or_insert_with(|| Histogram::new(&SECONDS_BUCKETS))
+        .observe(elapsed);
+    result
+}
+
+pub fn record_proof_bytes(air: &str, len: usize) {
+    observe_bytes(|registry| &mut registry.proof_bytes, air, len);
+}
+
+/// Records the size of a serialized witness, or of the input a witness is generated from.
+pub fn record_witness_bytes(air: &str, len: usize) {
+    observe_bytes(|registry| &mut registry.witness_bytes, air, len);
+}
+
+fn observe_bytes(
+    histograms: impl FnOnce(&mut Registry) -> &mut BTreeMap<String, Histogram>,
+    air: &str,
+    len: usize,
+) {
+    histograms(&mut REGISTRY.lock().unwrap())
+        .entry(air.to_string())
+        .or_insert_with(|| Histogram::new(&BYTES_BUCKETS))
+        .observe(len as f64);
+}
+
+/// Everything recorded so far in the Prometheus text format, along with the worker pool load and
+/// the process memory.
+pub fn render(in_flight: usize, capacity: usize) -> String {
+    let mut out = String::new();
+    {
+        let registry = REGISTRY.lock().unwrap();
+        header(
+            &mut out,
+            "prover_requests_total",
+            "counter",
+            "Prove and verify requests by endpoint and outcome.",
+        );
+        for ((air, endpoint, outcome), count) in &registry.requests {
+            let _ = writeln!(
+                out,
+                "prover_requests_total{{air=\"{}\",endpoint=\"{}\",outcome=\"{}\"}} {}",
+                escape(air),
+                endpoint,
+                outcome.label(),
+                count
+            );
+        }
+        header(
+            &mut out,
+            "prover_phase_seconds",
+            "histogram",
+            "Time spent generating traces, proving, serializing proofs and verifying.",
+        );
+        for ((air, phase), histogram) in &registry.phases {
+            let labels = format!("air=\"{}\",phase=\"{}\"", escape(air), phase.label());
+            histogram.render(&mut out, "prover_phase_seconds", &labels);
+        }
+        for (name, help, histograms) in [
+            (
+                "prover_proof_bytes",
+                "Size of serialized proofs.",
+                &registry.proof_bytes,
+            ),
+            (
+                "prover_witness_bytes",
+                "Size of serialized witnesses and of witness generation inputs.",
+                &registry.witness_bytes,
+            ),
+        ] {
+            header(&mut out, name, "histogram", help);
+            for (air, histogram) in histograms {
+                histogram.render(&mut out, name, &format!("air=\"{}\"", escape(air)));
+            }
+        }
+    }
+    header(
+        &mut out,
+        "prover_jobs_in_flight",
+        "gauge",
+        "Jobs queued or running on the worker pool.",
+    );
+    let _ = writeln!(out, "prover_jobs_in_flight {}", in_flight);
+    header(
+        &mut out,
+        "prover_jobs_capacity",
+        "gauge",
+        "Jobs the worker pool takes before turning requests away.",
+    );
+    let _ = writeln!(out, "prover_jobs_capacity {}", capacity);
+    for (name, field, help) in [
+        (
+            "process_resident_memory_bytes",
+            "VmRSS:",
+            "Resident memory size in bytes.",
+        ),
+        (
+            "process_virtual_memory_bytes",
+            "VmSize:",
+            "Virtual memory size in bytes.",
+        ),
+    ] {
+        if let Some(bytes) = proc_status_bytes(field) {
+            header(&mut out, name, "gauge", help);
+            let _ = writeln!(out, "{} {}", name, bytes);
+        }
+    }
+    out
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn escape(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Reads a `<field> <n> kB` line of `/proc/self/status`, which only exists on Linux.
+fn proc_status_bytes(field: &str) -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with(field))?;
+    let kib = line[field.len()..]
+        .split_whitespace()
+        .next()?
+        .parse::<u64>()
+        .ok()?;
+    Some(kib * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_buckets_are_cumulative() {
+        let mut histogram = Histogram::new(&[1.0, 2.0, 4.0]);
+        for value in [0.5, 1.0, 1.5, 3.0, 10.0] {
+            histogram.observe(value);
+        }
+        let mut out = String::new();
+        histogram.render(&mut out, "m", "air=\"a\"");
+        assert_eq!(
+            out,
+            "m_bucket{air=\"a\",le=\"1\"} 2\n\
+             m_bucket{air=\"a\",le=\"2\"} 3\n\
+             m_bucket{air=\"a\",le=\"4\"} 4\n\
+             m_bucket{air=\"a\",le=\"+Inf\"} 5\n\
+             m_sum{air=\"a\"} 16\n\
+             m_count{air=\"a\"} 5\n"
+        );
+    }
+
+    #[test]
+    fn empty_histogram_renders_zeros() {
+        let mut out = String::new();
+        Histogram::new(&[1.0]).render(&mut out, "m", "air=\"a\"");
+        assert_eq!(
+            out,
+            "m_bucket{air=\"a\",le=\"1\"} 0\n\
+             m_bucket{air=\"a\",le=\"+Inf\"} 0\n\
+             m_sum{air=\"a\"} 0\n\
+             m_count{air=\"a\"} 0\n"
+        );
+    }
+
+    #[test]
+    fn label_values_are_escaped() {
+        assert_eq!(escape("FibonacciAir"), "FibonacciAir");
+        assert_eq!(escape("a\\b\"c\nd"), "a\\\\b\\\"c\\nd");
+    }
+
+    #[test]
+    fn outcomes_follow_the_status() {
+        for (status, outcome) in [
+            (StatusCode::OK, Outcome::Success),
+            (StatusCode::ACCEPTED, Outcome::Success),
+            (StatusCode::BAD_REQUEST, Outcome::Failure),
+            (StatusCode::PAYLOAD_TOO_LARGE, Outcome::Failure),
+            (StatusCode::SERVICE_UNAVAILABLE, Outcome::Busy),
+            (StatusCode::INTERNAL_SERVER_ERROR, Outcome::Error),
+            (StatusCode::BAD_GATEWAY, Outcome::Error),
+        ] {
+            assert_eq!(Outcome::from_status(status), outcome, "{}", status);
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use log::info;
+use serde::Serialize;
+use warp::filters::BoxedFilter;
+use warp::{Filter, Reply};
+
+use crate::args::ServeArgs;
+use crate::backend::ProofBackend;
+use crate::fri::check_security;
+use crate::jobs::{JobRetention, JobStore};
+use crate::pool::WorkerPool;
+use crate::preset::{Preset, PresetReport};
+use crate::server::{backend_routes, metrics_route, ready_route};
+use crate::telemetry::request_span;
+use crate::vk::VerifyingKey;
+
+/// One entry of the `GET /circuits` listing.
+#[derive(Debug, Clone, Serialize)]
+pub struct CircuitListing {
+    pub name: String,
+    pub air: String,
+    pub field: String,
+    pub extension_degree: u64,
+    pub hash: String,
+    pub trace_width: u64,
+    pub log_blowup: u64,
+    pub num_queries: u64,
+    pub proof_of_work_bits: u64,
+    pub conjectured_security_bits: u64,
+    pub presets: Vec<PresetReport>,
+    /// Hex-encoded digest served by `GET /circuits/<name>/vk`.
+    pub vk: String,
+}
+
+impl CircuitListing {
+    fn new(name: &str, vk: &VerifyingKey, presets: &[Preset]) -> Self {
+        CircuitListing {
+            name: name.to_string(),
+            air: vk.air.clone(),
+            field: vk.field.clone(),
+            extension_degree: vk.extension_degree,
+            hash: vk.hash.clone(),
+            trace_width: vk.trace_width,
+            log_blowup: vk.log_blowup,
+            num_queries: vk.num_queries,
+            proof_of_work_bits: vk.proof_of_work_bits,
+            conjectured_security_bits: vk.conjectured_security_bits(),
+            presets: presets
+                .iter()
+                .map(|&preset| PresetReport::new(preset, vk.challenge_bits()))
+                .collect(),
+            vk: hex::encode(vk.digest()),
+        }
+    }
+}
+
+/// Several backends behind one process. Each circuit gets the single-backend endpoints under
+/// `/circuits/<name>/`, next to `GET /circuits` listing them all and the usual `GET /ready` and
+/// `GET /metrics`.
+pub struct MultiServer {
+    listing: Vec<CircuitListing>,
+    routes: Option<BoxedFilter<(Box<dyn Reply>,)>>,
+    /// Shared by all circuits.
+    pool: Arc<WorkerPool>,
+    spool_dir: Option<PathBuf>,
+    job_retention: JobRetention,
+    min_security_bits: u64,
+}
+
+impl MultiServer {
+    /// Takes the worker pool size, spool directory, job retention and security threshold from
+    /// `args`.
+    pub fn new(args: &ServeArgs) -> Self {
+        MultiServer {
+            listing: vec![],
+            routes: None,
+            pool: Arc::new(WorkerPool::new(args.pool)),
+            spool_dir: args.spool_dir.clone(),
+            job_retention: args.job_retention,
+            min_security_bits: args.min_security_bits,
+        }
+    }
+
+    /// Mounts `backend` under `/circuits/<name>/`. Fails if its configuration is below
+    /// `--min-security-bits`.
+    pub fn with<B: ProofBackend>(mut self, name: &str, backend: B) -> Result<Self, String> {
+        assert!(
+            !name.is_empty() && !name.contains('/'),
+            "invalid circuit name {:?}",
+            name
+        );
+        assert!(
+            self.listing.iter().all(|circuit| circuit.name != name),
+            "circuit {:?} mounted twice",
+            name
+        );
+        let vk = backend.verifying_key();
+        check_security(name, &vk, self.min_security_bits)?;
+        self.listing
+            .push(CircuitListing::new(name, &vk, &backend.presets()));
+        let spool_dir = self
+            .spool_dir
+            .as_ref()
+            .map(|spool_dir| spool_dir.join(name));
+        let jobs = Arc::new(
+            JobStore::new(spool_dir, self.job_retention).expect("unable to create spool dir"),
+        );
+        let circuit = warp::path("circuits")
+            .and(warp::path(name.to_string()))
+            .and(backend_routes(
+                Arc::new(backend),
+                self.pool.clone(),
+                jobs,
+                self.min_security_bits,
+            ))
+            .map(|reply| Box::new(reply) as Box<dyn Reply>)
+            .boxed();
+        self.routes = Some(match self.routes.take() {
+            Some(routes) => routes.or(circuit).unify().boxed(),
+            None => circuit,
+        });
+        Ok(self)
+    }
+
+    pub fn routes(self) -> BoxedFilter<(Box<dyn Reply>,)> {
+        let listing = serde_json::to_string(&self.listing).expect("unable to serialize listing");
+        let circuits = warp::get()
+            .and(warp::path("circuits"))
+            .and(warp::path::end())
+            .map(move || {
+                info!("Received circuits request.");
+                warp::reply::with_header(listing.clone(), "content-type", "application/json")
+            })
+            .map(|reply| Box::new(reply) as Box<dyn Reply>);
+        let ready = ready_route().map(|reply| Box::new(reply) as Box<dyn Reply>);
+        let metrics = metrics_route(self.pool).map(|reply| Box::new(reply) as Box<dyn Reply>);
+        let common = circuits.or(ready).unify().or(metrics).unify();
+        match self.routes {
+            Some(routes) => common.or(routes).unify().boxed(),
+            None => common.boxed(),
+        }
+    }
+
+    pub async fn serve(self, args: &ServeArgs) {
+        info!(
+            "Serving {} circuits on {:?}:{}.",
+            self.listing.len(),
+            args.host,
+            args.port
+        );
+        warp::serve(self.routes().with(warp::trace(request_span)))
+            .run((args.host, args.port))
+            .await;
+    }
+}
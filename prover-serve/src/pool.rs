@@ -0,0 +1,264 @@
+use std::collections::VecDeque;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use log::{error, info};
+use tokio::sync::{mpsc, oneshot};
+use tracing::Span;
+
+use crate::args::Flags;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Size of the [`WorkerPool`], set by `--workers` and `--queue-limit`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolConfig {
+    /// Jobs running at the same time.
+    pub workers: usize,
+    /// Jobs waiting for a worker before new ones are turned away.
+    pub queue_limit: usize,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        PoolConfig {
+            workers: thread::available_parallelism().map_or(1, |n| n.get()),
+            queue_limit: 16,
+        }
+    }
+}
+
+impl PoolConfig {
+    pub fn from_flags(flags: &mut Flags) -> Result<Self, String> {
+        let default = PoolConfig::default();
+        let config = PoolConfig {
+            workers: flags.take("workers")?.unwrap_or(default.workers),
+            queue_limit: flags.take("queue-limit")?.unwrap_or(default.queue_limit),
+        };
+        if config.workers == 0 {
+            return Err("--workers must be positive".to_string());
+        }
+        Ok(config)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoolError {
+    /// `queue_limit` jobs are already waiting for a worker.
+    Full,
+    /// The worker running the job went away without a result.
+    WorkerLost,
+    /// The job panicked, see the log for where.
+    Panicked,
+}
+
+/// Dedicated threads for proving and verification, so that long proofs do not block the async
+/// executor serving `/ready` and the other requests.
+pub struct WorkerPool {
+    sender: Sender<Job>,
+    /// Jobs queued or running.
+    pending: Arc<AtomicUsize>,
+    capacity: usize,
+    workers: usize,
+}
+
+impl WorkerPool {
+    pub fn new(config: PoolConfig) -> Self {
+        let (sender, receiver) = channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for i in 0..config.workers {
+            let receiver = receiver.clone();
+            thread::Builder::new()
+                .name(format!("prover-worker-{}", i))
+                .spawn(move || loop {
+                    let job = receiver.lock().unwrap().recv();
+                    match job {
+                        Ok(job) => job(),
+                        Err(_) => break,
+                    }
+                })
+                .expect("unable to spawn worker thread");
+        }
+        info!(
+            "Started {} workers with a queue limit of {}.",
+            config.workers, config.queue_limit
+        );
+        WorkerPool {
+            sender,
+            pending: Arc::new(AtomicUsize::new(0)),
+            capacity: config.workers + config.queue_limit,
+            workers: config.workers,
+        }
+    }
+
+    /// Runs `f` on a worker, or fails right away with [`PoolError::Full`] if the queue is full.
+    /// Panics are logged with `context` and reported as [`PoolError::Panicked`]. Jobs run in the
+    /// span they are submitted from.
+    pub async fn run<T, F>(&self, context: String, f: F) -> Result<T, PoolError>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let result = self.submit(context, f)?;
+        result.await.map_err(|_| PoolError::WorkerLost)?
+    }
+
+    /// Queues `f` without waiting for it, the result arrives on the returned channel.
+    pub fn submit<T, F>(
+        &self,
+        context: String,
+        f: F,
+    ) -> Result<oneshot::Receiver<Result<T, PoolError>>, PoolError>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        self.reserve(1)?;
+        let pending = PendingGuard(self.pending.clone());
+        let (result_sender, result_receiver) = oneshot::channel();
+        let span = Span::current();
+        let job: Job = Box::new(move || {
+            let _pending = pending;
+            let _span = span.enter();
+            let _ = result_sender.send(catch_panic(&context, f));
+        });
+        self.sender.send(job).map_err(|_| PoolError::WorkerLost)?;
+        Ok(result_receiver)
+    }
+
+    /// Queues `f` like [`submit`](Self::submit), without a result but with a handle taking it back
+    /// out of the queue.
+    pub fn submit_cancellable<F>(&self, context: String, f: F) -> Result<QueuedJob, PoolError>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.reserve(1)?;
+        let pending = PendingGuard(self.pending.clone());
+        let span = Span::current();
+        let job: Job = Box::new(move || {
+            let _pending = pending;
+            let _span = span.enter();
+            let _ = catch_panic(&context, f);
+        });
+        let queued = QueuedJob(Arc::new(Mutex::new(Some(job))));
+        let slot = queued.clone();
+        let take: Job = Box::new(move || {
+            let job = slot.0.lock().unwrap().take();
+            if let Some(job) = job {
+                job();
+            }
+        });
+        self.sender.send(take).map_err(|_| PoolError::WorkerLost)?;
+        Ok(queued)
+    }
+
+    /// Runs `jobs` on up to `workers` workers at once, holding that many slots until all are done,
+    /// or fails right away with [`PoolError::Full`]. Results come in the order of `jobs`, with
+    /// panics reported per job.
+    pub async fn run_batch<T, F>(
+        &self,
+        context: String,
+        jobs: Vec<F>,
+    ) -> Result<Vec<Result<T, PoolError>>, PoolError>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let num_jobs = jobs.len();
+        let lanes = num_jobs.min(self.workers);
+        if lanes == 0 {
+            return Ok(vec![]);
+        }
+        self.reserve(lanes)?;
+        let queue = Arc::new(Mutex::new(
+            jobs.into_iter().enumerate().collect::<VecDeque<_>>(),
+        ));
+        let (result_sender, mut result_receiver) = mpsc::unbounded_channel();
+        let span = Span::current();
+        for _ in 0..lanes {
+            let pending = PendingGuard(self.pending.clone());
+            let queue = queue.clone();
+            let result_sender = result_sender.clone();
+            let context = context.clone();
+            let span = span.clone();
+            let lane: Job = Box::new(move || {
+                let _pending = pending;
+                let _span = span.enter();
+                loop {
+                    let next = queue.lock().unwrap().pop_front();
+                    let Some((i, f)) = next else {
+                        break;
+                    };
+                    let result = catch_panic(&format!("{} item {}", context, i), f);
+                    let _ = result_sender.send((i, result));
+                }
+            });
+            self.sender.send(lane).map_err(|_| PoolError::WorkerLost)?;
+        }
+        drop(result_sender);
+        let mut results = (0..num_jobs).map(|_| None).collect::<Vec<_>>();
+        while let Some((i, result)) = result_receiver.recv().await {
+            results[i] = Some(result);
+        }
+        Ok(results
+            .into_iter()
+            .map(|result| result.unwrap_or(Err(PoolError::WorkerLost)))
+            .collect())
+    }
+
+    /// Jobs queued or running, with batches counting one per worker they occupy.
+    pub fn in_flight(&self) -> usize {
+        self.pending.load(Ordering::SeqCst)
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Takes `slots` slots at once, or none if they are not all free.
+    fn reserve(&self, slots: usize) -> Result<(), PoolError> {
+        self.pending
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |pending| {
+                (pending + slots <= self.capacity).then_some(pending + slots)
+            })
+            .map(|_| ())
+            .map_err(|_| PoolError::Full)
+    }
+}
+
+/// Runs `f`, turning a panic into [`PoolError::Panicked`] logged along with `context`.
+pub fn catch_panic<T>(context: &str, f: impl FnOnce() -> T) -> Result<T, PoolError> {
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("unknown panic payload");
+        error!("Panic in {}: {}", context, message);
+        PoolError::Panicked
+    })
+}
+
+/// A job waiting for a worker, see [`WorkerPool::submit_cancellable`].
+#[derive(Clone)]
+pub struct QueuedJob(Arc<Mutex<Option<Job>>>);
+
+impl QueuedJob {
+    /// Drops the job and frees its slot, unless a worker took it already. Returns whether it did.
+    pub fn cancel(&self) -> bool {
+        let job = self.0.lock().unwrap().take();
+        job.is_some()
+    }
+}
+
+/// Releases a slot of the pool once its job is done with, however that happens.
+struct PendingGuard(Arc<AtomicUsize>);
+
+impl Drop for PendingGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,178 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::args::Flags;
+use crate::fri::FriParams;
+use crate::proof::ProofConfig;
+
+/// Named FRI parameters a prove request can pick with `?preset=<name>` instead of the server's
+/// own. Proofs carry the preset, so that `/verify` checks them under the same parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Preset {
+    /// The smallest blowup, so the fastest commitments, paid for in queries.
+    Fast,
+    Balanced,
+    /// Few queries over a large blowup, for proofs that are cheap to store and verify.
+    SmallProof,
+    HighSecurity,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 4] = [
+        Preset::Fast,
+        Preset::Balanced,
+        Preset::SmallProof,
+        Preset::HighSecurity,
+    ];
+
+    pub fn fri_params(&self) -> FriParams {
+        let (log_blowup, num_queries, proof_of_work_bits) = match self {
+            Preset::Fast => (1, 84, 16),
+            Preset::Balanced => (2, 42, 16),
+            Preset::SmallProof => (4, 20, 20),
+            Preset::HighSecurity => (3, 38, 14),
+        };
+        FriParams {
+            log_blowup,
+            num_queries,
+            proof_of_work_bits,
+        }
+    }
+
+    /// Takes `--presets <name>,...` or `--presets none`, `None` if not given. See
+    /// [`allowed`](Self::allowed) for the presets a configuration ends up with.
+    pub fn from_flags(flags: &mut Flags) -> Result<Option<Vec<Self>>, String> {
+        let Some(names) = flags.take::<String>("presets")? else {
+            return Ok(None);
+        };
+        if names == "none" {
+            return Ok(Some(vec![]));
+        }
+        let mut presets = vec![];
+        for name in names.split(',') {
+            let preset = name
+                .trim()
+                .parse::<Preset>()
+                .map_err(|e| format!("invalid value {:?} for --presets: {}", names, e))?;
+            if !presets.contains(&preset) {
+                presets.push(preset);
+            }
+        }
+        presets.sort();
+        Ok(Some(presets))
+    }
+
+    /// The `listed` presets, or by default every preset with at least `min_security_bits` bits of
+    /// conjectured security under `config`. Listing one with fewer is an error.
+    pub fn allowed(
+        listed: Option<&[Preset]>,
+        config: &ProofConfig,
+        min_security_bits: u64,
+    ) -> Result<Vec<Self>, String> {
+        let bits = |preset: &Preset| {
+            preset
+                .fri_params()
+                .conjectured_security_bits(config.challenge_bits())
+        };
+        let Some(listed) = listed else {
+            return Ok(Preset::ALL
+                .into_iter()
+                .filter(|preset| bits(preset) >= min_security_bits)
+                .collect());
+        };
+        if let Some(preset) = listed
+            .iter()
+            .find(|preset| bits(preset) < min_security_bits)
+        {
+            return Err(format!(
+                "preset {} has {} bits of conjectured security over {}, below the minimum of {}",
+                preset,
+                bits(preset),
+                config,
+                min_security_bits
+            ));
+        }
+        Ok(listed.to_vec())
+    }
+}
+
+impl FromStr for Preset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fast" => Ok(Preset::Fast),
+            "balanced" => Ok(Preset::Balanced),
+            "small-proof" => Ok(Preset::SmallProof),
+            "high-security" => Ok(Preset::HighSecurity),
+            _ => Err(format!(
+                "expected fast, balanced, small-proof or high-security, not {:?}",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Preset::Fast => write!(f, "fast"),
+            Preset::Balanced => write!(f, "balanced"),
+            Preset::SmallProof => write!(f, "small-proof"),
+            Preset::HighSecurity => write!(f, "high-security"),
+        }
+    }
+}
+
+/// A preset as listed by `GET /config`.
+#[derive(Debug, Clone, Serialize)]
+pub struct PresetReport {
+    pub name: String,
+    pub log_blowup: u64,
+    pub num_queries: u64,
+    pub proof_of_work_bits: u64,
+    pub conjectured_security_bits: u64,
+}
+
+impl PresetReport {
+    /// Reports `preset` for challenges of `challenge_bits` bits.
+    pub fn new(preset: Preset, challenge_bits: u64) -> Self {
+        let fri = preset.fri_params();
+        PresetReport {
+            name: preset.to_string(),
+            log_blowup: fri.log_blowup as u64,
+            num_queries: fri.num_queries as u64,
+            proof_of_work_bits: fri.proof_of_work_bits as u64,
+            conjectured_security_bits: fri.conjectured_security_bits(challenge_bits),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_match_the_readme() {
+        for (preset, name, log_blowup, num_queries, proof_of_work_bits, bits) in [
+            (Preset::Fast, "fast", 1, 84, 16, 100),
+            (Preset::Balanced, "balanced", 2, 42, 16, 100),
+            (Preset::SmallProof, "small-proof", 4, 20, 20, 100),
+            (Preset::HighSecurity, "high-security", 3, 38, 14, 128),
+        ] {
+            let fri = preset.fri_params();
+            assert_eq!(
+                (fri.log_blowup, fri.num_queries, fri.proof_of_work_bits),
+                (log_blowup, num_queries, proof_of_work_bits),
+                "{}",
+                preset
+            );
+            assert_eq!(fri.conjectured_security_bits(u64::MAX), bits, "{}", preset);
+            assert_eq!(preset.to_string(), name);
+            assert_eq!(name.parse::<Preset>(), Ok(preset));
+        }
+        assert!("slow".parse::<Preset>().is_err());
+    }
+}
@@ -0,0 +1,208 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::args::Flags;
+use crate::backend::{VerifyError, VerifyErrorKind};
+use crate::dft::DftId;
+use crate::field::FieldId;
+use crate::hash::HashId;
+use crate::preset::Preset;
+
+/// The choices a server makes at startup that a proof is made with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofConfig {
+    pub field: FieldId,
+    pub hash: HashId,
+    /// Degree of the extension challenges are drawn from.
+    pub extension_degree: u8,
+    /// `None` for the circle PCS. Only the prover uses it, so it is recorded but not checked.
+    pub dft: Option<DftId>,
+}
+
+impl ProofConfig {
+    /// Proves over `field` with `hash`, its default extension and the default DFT.
+    pub fn new(field: FieldId, hash: HashId) -> Self {
+        ProofConfig {
+            field,
+            hash,
+            extension_degree: field.extension_degrees()[0],
+            dft: field.has_dft().then(DftId::default),
+        }
+    }
+
+    /// Bits of the extension challenges are drawn from, which bound the conjectured security.
+    pub fn challenge_bits(&self) -> u64 {
+        self.field.bits() * self.extension_degree as u64
+    }
+
+    /// Takes `--field`, `--hash`, `--extension-degree` and `--dft`, the hash falling back to
+    /// `default_hash`.
+    pub fn from_flags(flags: &mut Flags, default_hash: HashId) -> Result<Self, String> {
+        let mut config = ProofConfig::new(
+            FieldId::from_flags(flags)?,
+            HashId::from_flags(flags, default_hash)?,
+        );
+        if let Some(degree) = flags.take::<u8>("extension-degree")? {
+            if !config.field.extension_degrees().contains(&degree) {
+                return Err(format!(
+                    "{} has no degree-{} extension, expected one of {:?}",
+                    config.field,
+                    degree,
+                    config.field.extension_degrees()
+                ));
+            }
+            config.extension_degree = degree;
+        }
+        if let Some(dft) = DftId::from_flags(flags)? {
+            if !config.field.has_dft() {
+                return Err(format!(
+                    "{} proves with the circle PCS, which takes no --dft",
+                    config.field
+                ));
+            }
+            config.dft = Some(dft);
+        }
+        Ok(config)
+    }
+}
+
+/// As logged at startup, e.g. "BabyBear, degree-4 extension, Poseidon2 commitments,
+/// Radix2DitParallel DFT".
+impl fmt::Display for ProofConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}, degree-{} extension, {} commitments",
+            self.field, self.extension_degree, self.hash
+        )?;
+        if let Some(dft) = self.dft {
+            write!(f, ", {} DFT", dft)?;
+        }
+        Ok(())
+    }
+}
+
+/// A proof behind the configuration and preset it was made with, so that `/verify` can tell a
+/// proof made under another configuration from a bad one, and checks it with the preset's FRI
+/// parameters.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TaggedProof {
+    pub config: ProofConfig,
+    /// `None` for the server's own FRI parameters.
+    pub preset: Option<Preset>,
+    pub proof: ProofBody,
+}
+
+/// The proof inside a [`TaggedProof`], serialized as its postcard bytes. A proof just made is
+/// kept as is until the tagged proof is serialized, so that it is serialized once and within the
+/// serialization phase of `GET /metrics`.
+pub enum ProofBody {
+    Serialized(Vec<u8>),
+    Unserialized(Box<dyn SerializeProof>),
+}
+
+impl ProofBody {
+    pub fn new(proof: impl Serialize + Send + Sync + 'static) -> Self {
+        ProofBody::Unserialized(Box::new(proof))
+    }
+}
+
+/// Object-safe postcard serialization, for proofs whose type depends on the configuration.
+pub trait SerializeProof: Send + Sync {
+    fn to_bytes(&self) -> Vec<u8>;
+}
+
+impl<T: Serialize + Send + Sync> SerializeProof for T {
+    fn to_bytes(&self) -> Vec<u8> {
+        postcard::to_allocvec(self).expect("unable to serialize proof")
+    }
+}
+
+impl fmt::Debug for ProofBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProofBody::Serialized(bytes) => write!(f, "Serialized({} bytes)", bytes.len()),
+            ProofBody::Unserialized(_) => write!(f, "Unserialized"),
+        }
+    }
+}
+
+/// Postcard writes bytes and a `Vec<u8>` alike, so either way the body reads back as serialized.
+impl Serialize for ProofBody {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            ProofBody::Serialized(bytes) => serializer.serialize_bytes(bytes),
+            ProofBody::Unserialized(proof) => serializer.serialize_bytes(&proof.to_bytes()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ProofBody {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<u8>::deserialize(deserializer).map(ProofBody::Serialized)
+    }
+}
+
+impl TaggedProof {
+    /// The proof bytes, serialized if it was just made, if it was made under `expected`. A server
+    /// only verifies the configuration it was started with; proofs made under any other are
+    /// refused rather than dispatched.
+    pub fn check(&self, expected: &ProofConfig) -> Result<Cow<'_, [u8]>, VerifyError> {
+        if self.config.field != expected.field {
+            return Err(VerifyError::new(
+                VerifyErrorKind::FieldMismatch,
+                format!(
+                    "proof is over {} but this server verifies {}",
+                    self.config.field, expected.field
+                ),
+            ));
+        }
+        if self.config.hash != expected.hash {
+            return Err(VerifyError::new(
+                VerifyErrorKind::HashMismatch,
+                format!(
+                    "proof commits with {} but this server verifies {}",
+                    self.config.hash, expected.hash
+                ),
+            ));
+        }
+        if self.config.extension_degree != expected.extension_degree {
+            return Err(VerifyError::new(
+                VerifyErrorKind::ExtensionMismatch,
+                format!(
+                    "proof draws challenges from a degree-{} extension but this server uses \
+                     degree {}",
+                    self.config.extension_degree, expected.extension_degree
+                ),
+            ));
+        }
+        Ok(match &self.proof {
+            ProofBody::Serialized(bytes) => Cow::Borrowed(bytes),
+            ProofBody::Unserialized(proof) => Cow::Owned(proof.to_bytes()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unserialized_proofs_read_back_serialized() {
+        let config = ProofConfig::new(FieldId::BabyBear, HashId::Poseidon2);
+        let proof = TaggedProof {
+            config,
+            preset: None,
+            proof: ProofBody::new((7u64, vec![1u32, 2, 3])),
+        };
+        let bytes = postcard::to_allocvec(&proof).unwrap();
+        let read = postcard::from_bytes::<TaggedProof>(&bytes).unwrap();
+        assert_eq!(
+            read.check(&config).unwrap(),
+            postcard::to_allocvec(&(7u64, vec![1u32, 2, 3])).unwrap()
+        );
+        assert_eq!(postcard::to_allocvec(&read).unwrap(), bytes);
+    }
+}
@@ -0,0 +1,959 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use log::{debug, info};
+use serde::Serialize;
+use warp::{http::StatusCode, reply, Filter, Rejection, Reply};
+
+use crate::args::ServeArgs;
+use crate::backend::{ProofBackend, VerifyError, VerifyErrorKind};
+use crate::envelope::{encode_list, parse_list, Envelope, MAX_ENVELOPE_BYTES};
+use crate::fri::check_security;
+use crate::jobs::{JobStore, ProofLookup};
+use crate::metrics::{self, Outcome, Phase};
+use crate::pool::{catch_panic, PoolError, WorkerPool};
+use crate::preset::{Preset, PresetReport};
+use crate::telemetry::request_span;
+use crate::vk::{vk_matches, VerifyingKey};
+
+/// Seconds clients are asked to wait when the worker pool is full.
+const RETRY_AFTER_SECS: u64 = 5;
+
+/// `GET /ready`, `GET /metrics`, `GET /vk`, `GET /config`, `POST /prove`, `POST /prove/batch`,
+/// `POST /verify` and `/jobs` for `backend`.
+pub fn routes<B: ProofBackend>(
+    backend: Arc<B>,
+    pool: Arc<WorkerPool>,
+    jobs: Arc<JobStore>,
+    min_security_bits: u64,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone + Send + Sync + 'static {
+    backend_routes(backend, pool.clone(), jobs, min_security_bits)
+        .or(ready_route())
+        .or(metrics_route(pool))
+}
+
+/// A POST body of at most [`MAX_ENVELOPE_BYTES`]. Larger ones get `413 Payload Too Large` before
+/// they are read.
+fn body() -> impl Filter<Extract = (bytes::Bytes,), Error = Rejection> + Clone {
+    warp::body::content_length_limit(MAX_ENVELOPE_BYTES as u64).and(warp::body::bytes())
+}
+
+/// `GET /ready`, answered as soon as the server accepts connections.
+pub fn ready_route(
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone + Send + Sync + 'static {
+    let ready_time = chrono::offset::Utc::now();
+    warp::get().and(warp::path("ready")).map(move || {
+        info!("Received ready request.");
+        reply::with_status(format!("Ready since {:?}", ready_time), StatusCode::OK)
+    })
+}
+
+/// `GET /metrics` in the Prometheus text format, covering every backend served on `pool`.
+pub fn metrics_route(
+    pool: Arc<WorkerPool>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone + Send + Sync + 'static {
+    warp::get().and(warp::path("metrics")).map(move || {
+        debug!("Received metrics request.");
+        reply::with_header(
+            metrics::render(pool.in_flight(), pool.capacity()),
+            "content-type",
+            "text/plain; version=0.0.4",
+        )
+    })
+}
+
+/// `GET /vk`, `GET /config`, `POST /prove`, `POST /verify` and `/jobs` for `backend`, without
+/// `/ready`. Proving and verification run on `pool`.
+pub fn backend_routes<B: ProofBackend>(
+    backend: Arc<B>,
+    pool: Arc<WorkerPool>,
+    jobs: Arc<JobStore>,
+    min_security_bits: u64,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone + Send + Sync + 'static {
+    let verifying_key = backend.verifying_key();
+    let vk_digest = verifying_key.digest();
+    let config = ConfigReport::new(&verifying_key, &backend.presets(), min_security_bits);
+
+    let vk = warp::path("vk").map(move || {
+        info!("Received vk request.");
+        reply::with_status(vk_digest.to_vec(), StatusCode::OK)
+    });
+    let config = warp::path("config").map(move || {
+        info!("Received config request.");
+        reply::json(&config)
+    });
+    let prove = {
+        let backend = backend.clone();
+        let pool = pool.clone();
+        warp::path("prove")
+            .and(warp::path::end())
+            .and(warp::query::<HashMap<String, String>>())
+            .and(body())
+            .and_then(move |query: HashMap<String, String>, bytes: bytes::Bytes| {
+                info!("Received prove request.");
+                let backend = backend.clone();
+                let pool = pool.clone();
+                async move {
+                    let air = backend.verifying_key().air;
+                    let preset = match requested_preset(&*backend, &query) {
+                        Ok(preset) => preset,
+                        Err(e) => {
+                            metrics::record_request(&air, "prove", Outcome::Failure);
+                            return Ok::<_, Infallible>(preset_reply(e));
+                        }
+                    };
+                    let context = format!("{} /prove", air);
+                    let proof = pool.run(context, move || prove_bytes(&*backend, &bytes, preset));
+                    let reply = prove_reply(proof.await);
+                    metrics::record_request(&air, "prove", Outcome::from_status(reply.status()));
+                    Ok::<_, Infallible>(reply)
+                }
+            })
+    };
+    let prove_batch = {
+        let backend = backend.clone();
+        let pool = pool.clone();
+        warp::path!("prove" / "batch")
+            .and(warp::query::<HashMap<String, String>>())
+            .and(body())
+            .and_then(move |query: HashMap<String, String>, bytes: bytes::Bytes| {
+                info!("Received batch prove request.");
+                let backend = backend.clone();
+                let pool = pool.clone();
+                async move {
+                    let air = backend.verifying_key().air;
+                    let preset = match requested_preset(&*backend, &query) {
+                        Ok(preset) => preset,
+                        Err(e) => {
+                            metrics::record_request(&air, "prove_batch", Outcome::Failure);
+                            return Ok::<_, Infallible>(preset_reply(e));
+                        }
+                    };
+                    let witnesses = match parse_list(&bytes, MAX_ENVELOPE_BYTES) {
+                        Ok(items) => items
+                            .into_iter()
+                            .map(|item| bytes.slice_ref(item))
+                            .collect::<Vec<_>>(),
+                        Err(e) => {
+                            info!("Malformed batch: {}", e);
+                            metrics::record_request(&air, "prove_batch", Outcome::Failure);
+                            let reply = reply::with_status(
+                                format!("failure: {}", e),
+                                StatusCode::BAD_REQUEST,
+                            );
+                            return Ok::<_, Infallible>(reply.into_response());
+                        }
+                    };
+                    info!("Proving a batch of {} witnesses.", witnesses.len());
+                    let context = format!("{} /prove/batch", air);
+                    let jobs = witnesses
+                        .into_iter()
+                        .map(|witness| {
+                            let backend = backend.clone();
+                            move || prove_bytes(&*backend, &witness, preset)
+                        })
+                        .collect();
+                    let reply = match pool.run_batch(context, jobs).await {
+                        Ok(results) => {
+                            let items = results.into_iter().map(batch_item).collect::<Vec<_>>();
+                            let body = encode_list(items.iter().map(Vec::as_slice));
+                            reply::with_status(body, StatusCode::OK).into_response()
+                        }
+                        Err(e) => pool_error_reply(e),
+                    };
+                    let outcome = Outcome::from_status(reply.status());
+                    metrics::record_request(&air, "prove_batch", outcome);
+                    Ok(reply)
+                }
+            })
+    };
+    let prove_generated = {
+        let backend = backend.clone();
+        let pool = pool.clone();
+        warp::path!("prove" / String)
+            .and(warp::query::<HashMap<String, String>>())
+            .and(body())
+            .and_then(
+                move |format: String, query: HashMap<String, String>, bytes: bytes::Bytes| {
+                    info!("Received prove request for format {:?}.", format);
+                    let backend = backend.clone();
+                    let pool = pool.clone();
+                    async move {
+                        let air = backend.verifying_key().air;
+                        if !B::WITNESS_FORMATS.contains(&format.as_str()) {
+                            metrics::record_request(&air, "prove_generated", Outcome::Failure);
+                            let reply = reply::with_status(
+                                format!("failure: unknown witness format {:?}", format),
+                                StatusCode::NOT_FOUND,
+                            );
+                            return Ok::<_, Infallible>(reply.into_response());
+                        }
+                        let preset = match requested_preset(&*backend, &query) {
+                            Ok(preset) => preset,
+                            Err(e) => {
+                                metrics::record_request(&air, "prove_generated", Outcome::Failure);
+                                return Ok(preset_reply(e));
+                            }
+                        };
+                        let context = format!("{} /prove/{}", air, format);
+                        let envelope = pool.run(context, move || {
+                            prove_generated(&*backend, &format, &query, &bytes, preset)
+                        });
+                        let reply = prove_reply(envelope.await);
+                        let outcome = Outcome::from_status(reply.status());
+                        metrics::record_request(&air, "prove_generated", outcome);
+                        Ok(reply)
+                    }
+                },
+            )
+    };
+    let verify = {
+        let backend = backend.clone();
+        let pool = pool.clone();
+        warp::path("verify")
+            .and(warp::path::end())
+            .and(body())
+            .and(warp::header::optional::<String>("x-verifying-key"))
+            .and(warp::header::optional::<String>("accept"))
+            .and_then(
+                move |bytes: bytes::Bytes, vk: Option<String>, accept: Option<String>| {
+                    info!("Received verify request.");
+                    let backend = backend.clone();
+                    let pool = pool.clone();
+                    async move {
+                        let air = backend.verifying_key().air;
+                        let context = format!("{} /verify", air);
+                        let report = pool.run(context, move || {
+                            let start = Instant::now();
+                            let result = if vk_matches(&vk_digest, vk.as_deref()) {
+                                handle_verify(&*backend, &bytes)
+                            } else {
+                                Err(VerifyError::new(
+                                    VerifyErrorKind::VerifyingKeyMismatch,
+                                    "verifying key mismatch",
+                                ))
+                            };
+                            if let Err(e) = &result {
+                                info!("Verification failed ({:?}): {}", e.kind, e);
+                            }
+                            VerifyReport::new(result, start.elapsed())
+                        });
+                        let report = report.await;
+                        let outcome = match &report {
+                            Ok(report) if report.verdict == Verdict::Success => Outcome::Success,
+                            Ok(_) => Outcome::Failure,
+                            Err(PoolError::Full) => Outcome::Busy,
+                            Err(_) => Outcome::Error,
+                        };
+                        metrics::record_request(&air, "verify", outcome);
+                        let reply = match report {
+                            Ok(report)
+                                if accept
+                                    .is_some_and(|accept| accept.contains("application/json")) =>
+                            {
+                                let status = report.status();
+                                reply::with_status(reply::json(&report), status).into_response()
+                            }
+                            Ok(report) => reply::with_status(report.to_string(), report.status())
+                                .into_response(),
+                            Err(e) => pool_error_reply(e),
+                        };
+                        Ok::<_, Infallible>(reply)
+                    }
+                },
+            )
+    };
+
+    let verify_batch = {
+        let backend = backend.clone();
+        let pool = pool.clone();
+        warp::path!("verify" / "batch")
+            .and(body())
+            .and(warp::header::optional::<String>("x-verifying-key"))
+            .and(warp::header::optional::<String>("accept"))
+            .and_then(
+                move |bytes: bytes::Bytes, vk: Option<String>, accept: Option<String>| {
+                    info!("Received batch verify request.");
+                    let backend = backend.clone();
+                    let pool = pool.clone();
+                    async move {
+                        let air = backend.verifying_key().air;
+                        let envelopes = match parse_list(&bytes, MAX_ENVELOPE_BYTES) {
+                            Ok(items) => items
+                                .into_iter()
+                                .map(|item| bytes.slice_ref(item))
+                                .collect::<Vec<_>>(),
+                            Err(e) => {
+                                info!("Malformed batch: {}", e);
+                                metrics::record_request(&air, "verify_batch", Outcome::Failure);
+                                let reply = reply::with_status(
+                                    format!("failure: {}", e),
+                                    StatusCode::BAD_REQUEST,
+                                );
+                                return Ok::<_, Infallible>(reply.into_response());
+                            }
+                        };
+                        info!("Verifying a batch of {} proofs.", envelopes.len());
+                        let context = format!("{} /verify/batch", air);
+                        let report = pool.run(context, move || {
+                            let start = Instant::now();
+                            let results = if vk_matches(&vk_digest, vk.as_deref()) {
+                                handle_verify_batch(&*backend, &envelopes)
+                            } else {
+                                let mismatch = VerifyError::new(
+                                    VerifyErrorKind::VerifyingKeyMismatch,
+                                    "verifying key mismatch",
+                                );
+                                vec![Err(mismatch); envelopes.len()]
+                            };
+                            BatchVerifyReport::new(results, start.elapsed())
+                        });
+                        let reply = match report.await {
+                            Ok(report)
+                                if accept
+                                    .is_some_and(|accept| accept.contains("application/json")) =>
+                            {
+                                reply::json(&report).into_response()
+                            }
+                            Ok(report) => reply::with_status(report.to_string(), StatusCode::OK)
+                                .into_response(),
+                            Err(e) => pool_error_reply(e),
+                        };
+                        let outcome = Outcome::from_status(reply.status());
+                        metrics::record_request(&air, "verify_batch", outcome);
+                        Ok(reply)
+                    }
+                },
+            )
+    };
+
+    warp::post()
+        .and(
+            prove
+                .or(prove_batch)
+                .or(prove_generated)
+                .or(verify)
+                .or(verify_batch),
+        )
+        .or(warp::get().and(vk.or(config)))
+        .or(job_routes(backend, pool, jobs))
+}
+
+/// `POST /jobs/prove` queues a proof and returns its job, polled with `GET /jobs/<id>` until the
+/// proof can be fetched from `GET /jobs/<id>/proof`. `DELETE /jobs/<id>` cancels a job or
+/// forgets a finished one.
+fn job_routes<B: ProofBackend>(
+    backend: Arc<B>,
+    pool: Arc<WorkerPool>,
+    jobs: Arc<JobStore>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone + Send + Sync + 'static {
+    let submit = {
+        let jobs = jobs.clone();
+        warp::post()
+            .and(warp::path!("jobs" / "prove"))
+            .and(warp::query::<HashMap<String, String>>())
+            .and(body())
+            .map(move |query: HashMap<String, String>, bytes: bytes::Bytes| {
+                info!("Received job submission.");
+                let air = backend.verifying_key().air;
+                let preset = match requested_preset(&*backend, &query) {
+                    Ok(preset) => preset,
+                    Err(e) => {
+                        metrics::record_request(&air, "jobs_prove", Outcome::Failure);
+                        return preset_reply(e);
+                    }
+                };
+                let status = jobs.create();
+                let id = status.id.clone();
+                let backend = backend.clone();
+                let jobs_for_worker = jobs.clone();
+                let context = format!("{} job {}", air, id);
+                let air_for_worker = air.clone();
+                let submitted = pool.submit_cancellable(context.clone(), move || {
+                    if jobs_for_worker.start(&id) {
+                        let result =
+                            catch_panic(&context, || prove_bytes(&*backend, &bytes, preset));
+                        let outcome = match &result {
+                            Ok(Ok(_)) => Outcome::Success,
+                            Ok(Err(_)) => Outcome::Failure,
+                            Err(_) => Outcome::Error,
+                        };
+                        metrics::record_request(&air_for_worker, "jobs_prove", outcome);
+                        let result = result.unwrap_or_else(|_| Err("internal panic".to_string()));
+                        jobs_for_worker.finish(&id, result);
+                    }
+                });
+                match submitted {
+                    Ok(queued) => {
+                        jobs.queued(&status.id, queued);
+                        info!("Queued job {}.", status.id);
+                        reply::with_status(reply::json(&status), StatusCode::ACCEPTED)
+                            .into_response()
+                    }
+                    Err(e) => {
+                        jobs.remove(&status.id);
+                        metrics::record_request(&air, "jobs_prove", Outcome::Busy);
+                        pool_error_reply(e)
+                    }
+                }
+            })
+    };
+    let status = {
+        let jobs = jobs.clone();
+        warp::get()
+            .and(warp::path!("jobs" / String))
+            .map(move |id: String| {
+                info!("Received status request for job {}.", id);
+                match jobs.status(&id) {
+                    Some(status) => reply::json(&status).into_response(),
+                    None => unknown_job_reply(),
+                }
+            })
+    };
+    let proof = {
+        let jobs = jobs.clone();
+        warp::get()
+            .and(warp::path!("jobs" / String / "proof"))
+            .map(move |id: String| {
+                info!("Received proof request for job {}.", id);
+                match jobs.proof(&id) {
+                    ProofLookup::Ready(proof) => {
+                        reply::with_status(proof, StatusCode::OK).into_response()
+                    }
+                    ProofLookup::NotReady(status) => {
+                        let reason = match status.error {
+                            Some(error) => format!("failure: job failed: {}", error),
+                            None => format!("failure: job {:?}", status.state).to_lowercase(),
+                        };
+                        reply::with_status(reason, StatusCode::CONFLICT).into_response()
+                    }
+                    ProofLookup::Unknown => unknown_job_reply(),
+                }
+            })
+    };
+    let cancel = warp::delete()
+        .and(warp::path!("jobs" / String))
+        .map(move |id: String| {
+            info!("Received cancel request for job {}.", id);
+            match jobs.cancel(&id) {
+                Some(status) => reply::json(&status).into_response(),
+                None => unknown_job_reply(),
+            }
+        });
+
+    submit.or(status).or(proof).or(cancel)
+}
+
+fn unknown_job_reply() -> reply::Response {
+    reply::with_status("failure: unknown job", StatusCode::NOT_FOUND).into_response()
+}
+
+/// Serves `backend` until the process is killed. Fails without serving if its configuration is
+/// below `--min-security-bits`.
+pub async fn serve<B: ProofBackend>(backend: B, args: &ServeArgs) -> Result<(), String> {
+    let vk = backend.verifying_key();
+    check_security(&vk.air, &vk, args.min_security_bits)?;
+    info!(
+        "Serving {} with {} bits of conjectured security on {:?}:{}.",
+        vk.air,
+        vk.conjectured_security_bits(),
+        args.host,
+        args.port
+    );
+    let pool = Arc::new(WorkerPool::new(args.pool));
+    let jobs = Arc::new(
+        JobStore::new(args.spool_dir.clone(), args.job_retention)
+            .expect("unable to create spool dir"),
+    );
+    let routes = routes(Arc::new(backend), pool, jobs, args.min_security_bits);
+    warp::serve(routes.with(warp::trace(request_span)))
+        .run((args.host, args.port))
+        .await;
+    Ok(())
+}
+
+pub(crate) fn pool_error_reply(e: PoolError) -> reply::Response {
+    match e {
+        PoolError::Full => {
+            info!("Worker pool full, rejecting request.");
+            reply::with_header(
+                reply::with_status("failure: server busy", StatusCode::SERVICE_UNAVAILABLE),
+                "retry-after",
+                RETRY_AFTER_SECS.to_string(),
+            )
+            .into_response()
+        }
+        PoolError::WorkerLost => {
+            reply::with_status("failure: worker lost", StatusCode::INTERNAL_SERVER_ERROR)
+                .into_response()
+        }
+        PoolError::Panicked => {
+            reply::with_status("failure: internal panic", StatusCode::INTERNAL_SERVER_ERROR)
+                .into_response()
+        }
+    }
+}
+
+/// Body of `GET /config`: the parameters proofs are made and checked with.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigReport {
+    pub air: String,
+    pub field: String,
+    pub extension_degree: u64,
+    pub hash: String,
+    pub trace_width: u64,
+    pub log_blowup: u64,
+    pub num_queries: u64,
+    pub proof_of_work_bits: u64,
+    pub conjectured_security_bits: u64,
+    pub min_security_bits: u64,
+    /// What `?preset=<name>` may pick instead.
+    pub presets: Vec<PresetReport>,
+}
+
+impl ConfigReport {
+    fn new(vk: &VerifyingKey, presets: &[Preset], min_security_bits: u64) -> Self {
+        ConfigReport {
+            air: vk.air.clone(),
+            field: vk.field.clone(),
+            extension_degree: vk.extension_degree,
+            hash: vk.hash.clone(),
+            trace_width: vk.trace_width,
+            log_blowup: vk.log_blowup,
+            num_queries: vk.num_queries,
+            proof_of_work_bits: vk.proof_of_work_bits,
+            conjectured_security_bits: vk.conjectured_security_bits(),
+            min_security_bits,
+            presets: presets
+                .iter()
+                .map(|&preset| PresetReport::new(preset, vk.challenge_bits()))
+                .collect(),
+        }
+    }
+}
+
+fn prove_reply(result: Result<Result<Vec<u8>, String>, PoolError>) -> reply::Response {
+    match result {
+        Ok(Ok(body)) => reply::with_status(body, StatusCode::OK).into_response(),
+        Ok(Err(e)) => {
+            reply::with_status(format!("failure: {}", e), StatusCode::BAD_REQUEST).into_response()
+        }
+        Err(e) => pool_error_reply(e),
+    }
+}
+
+/// An item of the `POST /prove/batch` reply: 0 followed by the proof, or 1 followed by what went
+/// wrong.
+fn batch_item(result: Result<Result<Vec<u8>, String>, PoolError>) -> Vec<u8> {
+    let (status, payload) = match result {
+        Ok(Ok(proof)) => (0, proof),
+        Ok(Err(e)) => (1, e.into_bytes()),
+        Err(PoolError::Panicked) => (1, b"internal panic".to_vec()),
+        Err(PoolError::WorkerLost) => (1, b"worker lost".to_vec()),
+        Err(PoolError::Full) => (1, b"server busy".to_vec()),
+    };
+    let mut item = Vec::with_capacity(1 + payload.len());
+    item.push(status);
+    item.extend_from_slice(&payload);
+    item
+}
+
+/// The `preset` query parameter of a prove request, if it names one the backend allows.
+fn requested_preset<B: ProofBackend>(
+    backend: &B,
+    query: &HashMap<String, String>,
+) -> Result<Option<Preset>, String> {
+    let Some(name) = query.get("preset") else {
+        return Ok(None);
+    };
+    let preset = name.parse::<Preset>()?;
+    if !backend.presets().contains(&preset) {
+        return Err(format!("preset {} is not allowed", preset));
+    }
+    Ok(Some(preset))
+}
+
+fn preset_reply(e: String) -> reply::Response {
+    info!("Refused preset: {}", e);
+    reply::with_status(format!("failure: {}", e), StatusCode::BAD_REQUEST).into_response()
+}
+
+fn prove_witness<B: ProofBackend>(
+    backend: &B,
+    witness: B::Witness,
+    preset: Option<Preset>,
+) -> Result<B::Proof, String> {
+    let proof = match preset {
+        Some(preset) => backend.prove_preset(witness, preset),
+        None => backend.prove(witness),
+    };
+    proof.map_err(|e| {
+        info!("Unable to prove witness: {}", e);
+        e
+    })
+}
+
+/// Deserializes a witness and proves it, with the FRI parameters of `preset` if given, returning
+/// the serialized proof.
+fn prove_bytes<B: ProofBackend>(
+    backend: &B,
+    witness_bytes: &[u8],
+    preset: Option<Preset>,
+) -> Result<Vec<u8>, String> {
+    let air = backend.verifying_key().air;
+    metrics::record_witness_bytes(&air, witness_bytes.len());
+    let witness = postcard::from_bytes::<B::Witness>(witness_bytes).map_err(|e| {
+        info!("Unable to deserialize witness: {:?}", e);
+        format!("unable to deserialize witness: {}", e)
+    })?;
+    debug!("Deserialized witness.");
+    let proof = prove_witness(backend, witness, preset)?;
+    let proof = metrics::time(&air, Phase::Serialization, || {
+        postcard::to_allocvec(&proof).expect("unable to serialize proof")
+    });
+    metrics::record_proof_bytes(&air, proof.len());
+    Ok(proof)
+}
+
+/// `POST /verify` outcome, sent as JSON to clients accepting `application/json` and as
+/// "success" or "failure: <detail>" otherwise.
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifyReport {
+    pub verdict: Verdict,
+    pub error: Option<VerifyErrorKind>,
+    pub detail: Option<String>,
+    pub verification_time_ms: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    Success,
+    Failure,
+}
+
+impl VerifyReport {
+    fn new(result: Result<(), VerifyError>, elapsed: Duration) -> Self {
+        let verification_time_ms = elapsed.as_secs_f64() * 1000.0;
+        match result {
+            Ok(()) => VerifyReport {
+                verdict: Verdict::Success,
+                error: None,
+                detail: None,
+                verification_time_ms,
+            },
+            Err(e) => VerifyReport {
+                verdict: Verdict::Failure,
+                error: Some(e.kind),
+                detail: Some(e.detail),
+                verification_time_ms,
+            },
+        }
+    }
+
+    /// 400 for bodies that are not a well-formed envelope, 200 for any verdict on a proof.
+    fn status(&self) -> StatusCode {
+        match self.error {
+            Some(VerifyErrorKind::LengthFraming) => StatusCode::BAD_REQUEST,
+            _ => StatusCode::OK,
+        }
+    }
+}
+
+impl fmt::Display for VerifyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.verdict, &self.detail) {
+            (Verdict::Success, _) => write!(f, "success"),
+            (Verdict::Failure, Some(detail)) => write!(f, "failure: {}", detail),
+            (Verdict::Failure, None) => write!(f, "failure"),
+        }
+    }
+}
+
+/// `POST /verify/batch` outcome: one verdict per envelope, in order, sent as JSON to clients
+/// accepting `application/json` and as one "success" or "failure: <detail>" line each otherwise.
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchVerifyReport {
+    pub verdicts: Vec<BatchVerdict>,
+    pub verification_time_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchVerdict {
+    pub verdict: Verdict,
+    pub error: Option<VerifyErrorKind>,
+    pub detail: Option<String>,
+}
+
+impl BatchVerifyReport {
+    fn new(results: Vec<Result<(), VerifyError>>, elapsed: Duration) -> Self {
+        let verdicts = results
+            .into_iter()
+            .map(|result| match result {
+                Ok(()) => BatchVerdict {
+                    verdict: Verdict::Success,
+                    error: None,
+                    detail: None,
+                },
+                Err(e) => BatchVerdict {
+                    verdict: Verdict::Failure,
+                    error: Some(e.kind),
+                    detail: Some(e.detail),
+                },
+            })
+            .collect();
+        BatchVerifyReport {
+            verdicts,
+            verification_time_ms: elapsed.as_secs_f64() * 1000.0,
+        }
+    }
+}
+
+impl fmt::Display for BatchVerifyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for verdict in &self.verdicts {
+            match (verdict.verdict, &verdict.detail) {
+                (Verdict::Success, _) => writeln!(f, "success")?,
+                (Verdict::Failure, Some(detail)) => writeln!(f, "failure: {}", detail)?,
+                (Verdict::Failure, None) => writeln!(f, "failure")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn handle_verify<B: ProofBackend>(
+    backend: &B,
+    pis_and_proof_bytes: &[u8],
+) -> Result<(), VerifyError> {
+    let (pis, proof) = decode_envelope::<B>(pis_and_proof_bytes)?;
+    let air = backend.verifying_key().air;
+    metrics::time(&air, Phase::Verification, || backend.verify(&pis, &proof))
+}
+
+/// Decodes every envelope and hands those that decode to [`ProofBackend::verify_batch`] at once.
+fn handle_verify_batch<B: ProofBackend>(
+    backend: &B,
+    envelopes: &[bytes::Bytes],
+) -> Vec<Result<(), VerifyError>> {
+    let mut results = Vec::with_capacity(envelopes.len());
+    let mut batch = vec![];
+    for envelope in envelopes {
+        match decode_envelope::<B>(envelope) {
+            Ok(decoded) => {
+                batch.push(decoded);
+                results.push(Ok(()));
+            }
+            Err(e) => results.push(Err(e)),
+        }
+    }
+    let air = backend.verifying_key().air;
+    let verified = metrics::time(&air, Phase::Verification, || backend.verify_batch(&batch));
+    assert_eq!(verified.len(), batch.len(), "one verdict per proof");
+    let decoded = results.iter_mut().filter(|result| result.is_ok());
+    for (result, verdict) in decoded.zip(verified) {
+        *result = verdict;
+    }
+    for result in results.iter().filter_map(|result| result.as_ref().err()) {
+        info!("Verification failed ({:?}): {}", result.kind, result);
+    }
+    results
+}
+
+fn decode_envelope<B: ProofBackend>(
+    pis_and_proof_bytes: &[u8],
+) -> Result<(B::PublicInputs, B::Proof), VerifyError> {
+    let Envelope {
+        pis: pis_bytes,
+        proof: proof_bytes,
+    } = Envelope::parse(pis_and_proof_bytes, MAX_ENVELOPE_BYTES)
+        .map_err(|e| VerifyError::new(VerifyErrorKind::LengthFraming, e.to_string()))?;
+
+    let pis = postcard::from_bytes::<B::PublicInputs>(pis_bytes).map_err(|e| {
+        VerifyError::new(
+            VerifyErrorKind::PublicInputDecode,
+            format!("unable to deserialize pis: {}", e),
+        )
+    })?;
+    debug!("Deserialized pis.");
+    let proof = postcard::from_bytes::<B::Proof>(proof_bytes).map_err(|e| {
+        VerifyError::new(
+            VerifyErrorKind::ProofDecode,
+            format!("unable to deserialize proof: {}", e),
+        )
+    })?;
+    debug!("Deserialized proof.");
+    Ok((pis, proof))
+}
+
+/// Builds the witness from a `POST /prove/<format>` body and proves it, returning the public
+/// inputs and proof in the `POST /verify` envelope.
+fn prove_generated<B: ProofBackend>(
+    backend: &B,
+    format: &str,
+    query: &HashMap<String, String>,
+    body: &[u8],
+    preset: Option<Preset>,
+) -> Result<Vec<u8>, String> {
+    let air = backend.verifying_key().air;
+    metrics::record_witness_bytes(&air, body.len());
+    let (witness, pis) = backend.generate(format, query, body).map_err(|e| {
+        info!("Unable to generate witness from {:?} input: {}", format, e);
+        e
+    })?;
+    debug!("Generated witness.");
+    let proof = prove_witness(backend, witness, preset)?;
+    let (pis, proof) = metrics::time(&air, Phase::Serialization, || {
+        let pis = postcard::to_allocvec(&pis).expect("unable to serialize pis");
+        let proof = postcard::to_allocvec(&proof).expect("unable to serialize proof");
+        (pis, proof)
+    });
+    metrics::record_proof_bytes(&air, proof.len());
+    Ok(Envelope {
+        pis: &pis,
+        proof: &proof,
+    }
+    .encode())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jobs::JobRetention;
+    use crate::pool::PoolConfig;
+
+    /// Proves a number by returning it, and verifies that the proof is the public input.
+    struct MockBackend;
+
+    impl ProofBackend for MockBackend {
+        type Witness = u64;
+        type PublicInputs = u64;
+        type Proof = u64;
+
+        const WITNESS_FORMATS: &'static [&'static str] = &["mock"];
+
+        fn prove(&self, witness: u64) -> Result<u64, String> {
+            Ok(witness)
+        }
+
+        fn prove_preset(&self, witness: u64, _preset: Preset) -> Result<u64, String> {
+            Ok(witness)
+        }
+
+        fn presets(&self) -> Vec<Preset> {
+            vec![Preset::Fast]
+        }
+
+        fn verify(&self, pis: &u64, proof: &u64) -> Result<(), VerifyError> {
+            if pis == proof {
+                Ok(())
+            } else {
+                Err(VerifyError::new(
+                    VerifyErrorKind::InvalidPublicInputs,
+                    "wrong number",
+                ))
+            }
+        }
+
+        fn verifying_key(&self) -> VerifyingKey {
+            VerifyingKey {
+                air: "MockAir".to_string(),
+                field: "BabyBear".to_string(),
+                extension_degree: 4,
+                trace_width: 1,
+                log_blowup: 1,
+                num_queries: 100,
+                proof_of_work_bits: 16,
+                hash: "Keccak256".to_string(),
+                hash_constants_digest: [0; 32],
+            }
+        }
+    }
+
+    fn mock_routes(
+    ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone + Send + Sync + 'static
+    {
+        let pool = Arc::new(WorkerPool::new(PoolConfig {
+            workers: 1,
+            queue_limit: 4,
+        }));
+        let jobs = Arc::new(JobStore::new(None, JobRetention::default()).unwrap());
+        routes(Arc::new(MockBackend), pool, jobs, 64)
+    }
+
+    #[tokio::test]
+    async fn oversize_bodies_are_refused() {
+        let routes = mock_routes();
+        let body = vec![0u8; MAX_ENVELOPE_BYTES + 1];
+        for path in [
+            "/prove",
+            "/prove/batch",
+            "/prove/mock",
+            "/verify",
+            "/verify/batch",
+            "/jobs/prove",
+        ] {
+            let reply = warp::test::request()
+                .method("POST")
+                .path(path)
+                .body(&body)
+                .reply(&routes)
+                .await;
+            assert_eq!(reply.status(), StatusCode::PAYLOAD_TOO_LARGE, "{}", path);
+        }
+    }
+
+    #[tokio::test]
+    async fn bodies_within_the_limit_are_read() {
+        let routes = mock_routes();
+        let reply = warp::test::request()
+            .method("POST")
+            .path("/prove")
+            .body(postcard::to_allocvec(&7u64).unwrap())
+            .reply(&routes)
+            .await;
+        assert_eq!(reply.status(), StatusCode::OK);
+        assert_eq!(postcard::from_bytes::<u64>(reply.body()).unwrap(), 7);
+    }
+
+    #[tokio::test]
+    async fn unknown_and_disallowed_presets_are_refused() {
+        let routes = mock_routes();
+        let witness = postcard::to_allocvec(&7u64).unwrap();
+        for (query, status, body) in [
+            ("?preset=fast", StatusCode::OK, None),
+            (
+                "?preset=slow",
+                StatusCode::BAD_REQUEST,
+                Some(
+                    "failure: expected fast, balanced, small-proof or high-security, not \"slow\"",
+                ),
+            ),
+            (
+                "?preset=balanced",
+                StatusCode::BAD_REQUEST,
+                Some("failure: preset balanced is not allowed"),
+            ),
+        ] {
+            for path in ["/prove", "/jobs/prove"] {
+                let reply = warp::test::request()
+                    .method("POST")
+                    .path(&format!("{}{}", path, query))
+                    .body(&witness)
+                    .reply(&routes)
+                    .await;
+                let expected = match (path, status) {
+                    ("/jobs/prove", StatusCode::OK) => StatusCode::ACCEPTED,
+                    _ => status,
+                };
+                assert_eq!(reply.status(), expected, "{}{}", path, query);
+                if let Some(body) = body {
+                    assert_eq!(reply.body(), body, "{}{}", path, query);
+                }
+            }
+        }
+    }
+}
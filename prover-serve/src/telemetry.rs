@@ -0,0 +1,74 @@
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use tracing::Span;
+use tracing_forest::ForestLayer;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{EnvFilter, Registry};
+
+/// How spans are logged, set by `--trace`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// Plain `env_logger` lines, without spans.
+    #[default]
+    Off,
+    /// Each request as a tree of its spans with their timings, printed when it completes.
+    Tree,
+    /// One JSON object per event and per closed span, with the spans it happened in.
+    Json,
+}
+
+impl FromStr for TraceFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(TraceFormat::Off),
+            "tree" => Ok(TraceFormat::Tree),
+            "json" => Ok(TraceFormat::Json),
+            _ => Err(format!("expected tree, json or off, not {:?}", s)),
+        }
+    }
+}
+
+/// Installs the logger for `format`. Either way `RUST_LOG` picks what is logged; spans default to
+/// `info` and up so that there is something to see, and `log` records end up among them.
+pub fn init(format: TraceFormat) {
+    let filter = || EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    match format {
+        TraceFormat::Off => env_logger::init(),
+        TraceFormat::Tree => Registry::default()
+            .with(filter())
+            .with(ForestLayer::default())
+            .init(),
+        TraceFormat::Json => tracing_subscriber::fmt()
+            .json()
+            .with_env_filter(filter())
+            .with_current_span(true)
+            .with_span_list(true)
+            .with_span_events(FmtSpan::CLOSE)
+            .init(),
+    }
+}
+
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(0);
+
+/// The span a request runs in, carried over to the worker proving or verifying it. Its id is
+/// the `X-Request-Id` header when the client sends one.
+pub fn request_span(info: warp::trace::Info) -> Span {
+    let id = match info
+        .request_headers()
+        .get("x-request-id")
+        .and_then(|id| id.to_str().ok())
+    {
+        Some(id) => id.to_string(),
+        None => format!("{:016x}", NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed)),
+    };
+    tracing::info_span!(
+        "request",
+        id = %id,
+        method = %info.method(),
+        path = %info.path(),
+    )
+}
@@ -0,0 +1,66 @@
+use serde::{Deserialize, Serialize};
+use tiny_keccak::Hasher;
+
+use crate::field::FieldId;
+use crate::fri::conjectured_security_bits;
+
+/// Everything a verifier has to agree on with the prover. Proofs are only interchangeable
+/// between servers whose verifying keys have the same [`digest`](VerifyingKey::digest).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifyingKey {
+    pub air: String,
+    pub field: String,
+    pub extension_degree: u64,
+    pub trace_width: u64,
+    pub log_blowup: u64,
+    pub num_queries: u64,
+    pub proof_of_work_bits: u64,
+    pub hash: String,
+    /// [`keccak256`] of the serialized hash constants, zero for hashes without any.
+    pub hash_constants_digest: [u8; 32],
+}
+
+impl VerifyingKey {
+    /// Keccak-256 of the postcard-serialized key, served by `GET /vk`.
+    pub fn digest(&self) -> [u8; 32] {
+        keccak256(&postcard::to_allocvec(self).expect("unable to serialize vk"))
+    }
+
+    pub fn conjectured_security_bits(&self) -> u64 {
+        conjectured_security_bits(
+            self.log_blowup,
+            self.num_queries,
+            self.proof_of_work_bits,
+            self.challenge_bits(),
+        )
+    }
+
+    /// Bits of the extension challenges are drawn from, unbounded for fields other than
+    /// [`FieldId`]'s.
+    pub fn challenge_bits(&self) -> u64 {
+        FieldId::ALL
+            .into_iter()
+            .find(|field| field.to_string() == self.field)
+            .map_or(u64::MAX, |field| {
+                field.bits().saturating_mul(self.extension_degree)
+            })
+    }
+}
+
+pub fn keccak256(bytes: &[u8]) -> [u8; 32] {
+    let mut hasher = tiny_keccak::Keccak::v256();
+    let mut output = [0u8; 32];
+    hasher.update(bytes);
+    hasher.finalize(&mut output);
+    output
+}
+
+/// Checks the hex-encoded vk a client passed to `/verify`, if any, against our own digest.
+pub(crate) fn vk_matches(expected: &[u8; 32], provided_hex: Option<&str>) -> bool {
+    match provided_hex {
+        None => true,
+        Some(provided_hex) => {
+            hex::decode(provided_hex.trim()).is_ok_and(|provided| provided == expected)
+        }
+    }
+}
@@ -40,6 +40,13 @@ if __name__ == '__main__':
     # check success message
     assert response.text == "success", f"Failed to verify proof: {response.text}"
     print("Proof verified successfully")
+
+    # structured result
+    json_headers = dict(verify_headers, Accept='application/json')
+    response = requests.post(url+"/verify", headers=json_headers, data=verifier_input)
+    result = response.json()
+    assert result["verdict"] == "success", f"Failed to verify proof: {result}"
+    print("Structured result:", result)
     
     # try tempered proof
     print("Test tempered proof...")
@@ -48,15 +55,32 @@ if __name__ == '__main__':
     random_byte_index = random.randint(0, len(proof) - 1)
     random_bit_index = random.randint(0, 7)
     tempered_proof = proof[:random_byte_index] + bytes([proof[random_byte_index] ^ (1 << random_bit_index)]) + proof[random_byte_index+1:]
-    tempered_input = pis_len + proof_len + witness + tempered_proof
-    try:
-        response = requests.post(url+"/verify", headers=verify_headers, data=tempered_input)
-        # check failure message
-        assert response.text == "failure", f"Failed to detect tempered proof: {response.text}"
-        print("Tempered proof detected successfully")
-    except requests.exceptions.ConnectionError:
-        print("Tempered proof detected but service panicked due to misfunctioning library error catching")
+    tempered_input = pis_len + proof_len + pis + tempered_proof
+    response = requests.post(url+"/verify", headers=json_headers, data=tempered_input)
+    # a rejected proof is a 200 verdict, a panic inside the verifier would be a 500
+    assert response.status_code == 200, f"Failed to detect tempered proof: {response.status_code} {response.text}"
+    result = response.json()
+    assert result["verdict"] == "failure", f"Failed to detect tempered proof: {result}"
+    assert result["error"] in (
+        "proof_decode", "field_mismatch", "hash_mismatch", "extension_mismatch",
+        "preset_not_allowed", "proof_shape", "ood_mismatch", "fri_failure",
+    ), f"Tempered proof rejected for the wrong reason: {result}"
+    print("Tempered proof detected successfully:", result["error"], result["detail"])
+
 
+    # try malformed envelopes
+    print("Test malformed verify bodies...")
+    malformed = {
+        "short header": verifier_input[:10],
+        "truncated proof": verifier_input[:-1],
+        "trailing bytes": verifier_input + b"\x00",
+        "huge length prefix": (2**64 - 1).to_bytes(8, byteorder='little') + proof_len + pis + proof,
+    }
+    for name, body in malformed.items():
+        response = requests.post(url+"/verify", headers={'Content-Type': 'application/octet-stream'}, data=body)
+        assert response.status_code == 400, f"Failed to reject {name}: {response.status_code} {response.text}"
+        assert response.text.startswith("failure"), f"Failed to reject {name}: {response.text}"
+        print(f"Rejected {name}:", response.text)
 
     # try prove using witness with invalid length
     print("Test invalid witness length...")
@@ -65,10 +89,12 @@ if __name__ == '__main__':
         'Content-Type': 'application/octet-stream',
         'Content-Length': str(len(tempered_witness)),
     }
-    try:
-        response = requests.post(url+"/prove", headers=prove_headers, data=tempered_witness)
-        # check 400
-        assert response.status_code == 400, f"Failed to detect invalid witness length: {response.text}"
-        print("Invalid witness length detected successfully")
-    except requests.exceptions.ConnectionError:
-        print("Invalid witness length detected but service panicked due to misfunctioning library error catching")
+    response = requests.post(url+"/prove", headers=prove_headers, data=tempered_witness)
+    # check 400
+    assert response.status_code == 400, f"Failed to detect invalid witness length: {response.text}"
+    print("Invalid witness length detected successfully")
+
+    # the service keeps working after rejected requests
+    response = requests.get(url+"/ready")
+    assert response.status_code == 200, f"Service not ready after failures: {response.text}"
+    print("Service still ready")